@@ -0,0 +1,141 @@
+// Beschleunigungsstrukturen für die Suche nach dem nächsten Schnittpunkt eines Strahls mit den Dreiecken der Szene
+
+use crate::bounding_box::BoundingBox;
+use crate::bvh::Bvh;
+use crate::ray::{Hit, Ray, EPSILON};
+use crate::mesh::Mesh;
+use anyhow::anyhow;
+use glam::f32::Vec3A;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+#[test]
+fn test_identical_hits() {
+    let mesh = Mesh::from_triangles(&crate::bounding_box::random_triangles(400));
+    let accelerators: Vec<Box<dyn Accelerator>> = [
+        AcceleratorKind::BruteForce,
+        AcceleratorKind::BoundingBox,
+        AcceleratorKind::Bvh,
+    ]
+    .iter()
+    .map(|&kind| build(kind, &mesh, 3))
+    .collect();
+    let mut hits = 0;
+    for i in 0..900 {
+        // rays from all around the triangle soup, through and past it
+        let angle = i as f32 * 0.37;
+        let origin = Vec3A::new(3.0 * angle.cos(), (i % 7) as f32 - 3.0, 3.0 * angle.sin());
+        let target = Vec3A::new(
+            (i % 30) as f32 / 12.0 - 1.25,
+            (i / 30) as f32 / 12.0 - 1.25,
+            0.1,
+        );
+        let ray = Ray::new(origin, target - origin);
+        let expected = accelerators[0].intersect(&mesh, &ray, f32::INFINITY);
+        for a in &accelerators[1..] {
+            assert_eq!(
+                a.intersect(&mesh, &ray, f32::INFINITY),
+                expected,
+                "{:?}",
+                a
+            );
+            assert_eq!(
+                a.intersect(&mesh, &ray, 1.0),
+                accelerators[0].intersect(&mesh, &ray, 1.0)
+            );
+        }
+        hits += expected.is_some() as usize;
+    }
+    // make sure the test is not vacuous
+    assert!(hits > 100 && hits < 900);
+}
+
+#[test]
+fn test_parse_kind() {
+    assert_eq!(
+        "bvh".parse::<AcceleratorKind>().unwrap(),
+        AcceleratorKind::Bvh
+    );
+    assert_eq!(
+        "octree".parse::<AcceleratorKind>().unwrap(),
+        AcceleratorKind::BoundingBox
+    );
+    assert!("kd".parse::<AcceleratorKind>().is_err());
+}
+
+pub trait Accelerator: Debug + Send + Sync {
+    // Nächster Schnittpunkt mit Abstand in (EPSILON, t_max), mesh muss das Netz sein, über dem die Struktur gebaut wurde
+    fn intersect(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit>;
+}
+
+// Testet jedes Dreieck, nur für sehr kleine Szenen sinnvoll
+#[derive(Debug)]
+pub struct BruteForce;
+
+impl Accelerator for BruteForce {
+    fn intersect(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit> {
+        let mut nearest: Option<Hit> = None;
+        for i in 0..mesh.len() {
+            if let Some((t, u, v)) = mesh.intersect(i, ray) {
+                if t > EPSILON && t < nearest.map_or(t_max, |h| h.t) {
+                    nearest = Some(Hit {
+                        t,
+                        u,
+                        v,
+                        triangle: i,
+                    });
+                }
+            }
+        }
+        nearest
+    }
+}
+
+impl Accelerator for BoundingBox {
+    fn intersect(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit> {
+        BoundingBox::intersect(self, mesh, ray, t_max)
+    }
+}
+
+impl Accelerator for Bvh {
+    fn intersect(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit> {
+        Bvh::intersect(self, mesh, ray, t_max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceleratorKind {
+    BruteForce,
+    // Raumteilung mit der Unterteilungstiefe aus <raumteilung>
+    BoundingBox,
+    Bvh,
+}
+
+impl FromStr for AcceleratorKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "brute" => Ok(Self::BruteForce),
+            "octree" => Ok(Self::BoundingBox),
+            "bvh" => Ok(Self::Bvh),
+            _ => Err(anyhow!(
+                "Unknown accelerator: {} (expected brute, octree or bvh)",
+                s
+            )),
+        }
+    }
+}
+
+// unterteilung is only used by AcceleratorKind::BoundingBox
+pub fn build(
+    kind: AcceleratorKind,
+    mesh: &Mesh,
+    unterteilung: u32,
+) -> Box<dyn Accelerator> {
+    match kind {
+        AcceleratorKind::BruteForce => Box::new(BruteForce),
+        AcceleratorKind::BoundingBox => Box::new(BoundingBox::build(mesh, unterteilung)),
+        AcceleratorKind::Bvh => Box::new(Bvh::build(mesh)),
+    }
+}
@@ -1,12 +1,14 @@
+use crate::mesh::Mesh;
+use crate::ray::{Hit, Ray, EPSILON};
 use crate::triangle::Triangle;
-use core::f32::{INFINITY, NEG_INFINITY};
 use glam::f32::Vec3A;
 use std::iter::Iterator;
 use std::vec::Vec;
-//import SchnittEreignis;
-const SPLIT_TRIANGLES_MIN: i8 = 4;
+// Boxen mit weniger Dreiecken werden nicht weiter unterteilt
+const SPLIT_TRIANGLES_MIN: usize = 4;
 
 #[test]
+#[allow(non_snake_case)]
 fn testAround() {
     let b = BoundingBox::around(
         &mut vec![Triangle::new(
@@ -20,32 +22,120 @@ fn testAround() {
     assert_eq!(b.max, Vec3A::new(1.0, 4.0, 3.0));
 }
 
-#[derive(Default)]
+#[cfg(test)]
+// deterministic pseudo random triangle soup in [-1, 1]³
+pub fn random_triangles(n: usize) -> Vec<Triangle> {
+    let mut state: u32 = 12345;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+    let mut v = move || Vec3A::new(next(), next(), next());
+    (0..n)
+        .map(|_| {
+            let a = v();
+            Triangle::new(a, a + 0.2 * v(), a + 0.2 * v())
+        })
+        .collect()
+}
+
+#[test]
+fn test_build() {
+    let triangles = random_triangles(200);
+    let b = BoundingBox::build(&Mesh::from_triangles(&triangles), 2);
+    assert_eq!(b.children.len(), 8);
+    assert!(b.triangles.is_empty());
+    let leaf = &b.children[0].children[0];
+    assert!(leaf.children.is_empty());
+    assert!(!leaf.triangles.is_empty() && leaf.triangles.len() < 200);
+    // every triangle ends up in at least one leaf
+    let mut seen = vec![false; triangles.len()];
+    fn visit(b: &BoundingBox, seen: &mut Vec<bool>) {
+        b.triangles.iter().for_each(|&i| seen[i] = true);
+        b.children.iter().for_each(|c| visit(c, seen));
+    }
+    visit(&b, &mut seen);
+    assert!(seen.iter().all(|&s| s));
+}
+
+#[test]
+fn test_intersect_matches_brute_force() {
+    let triangles = random_triangles(300);
+    let mesh = Mesh::from_triangles(&triangles);
+    let b = BoundingBox::build(&mesh, 3);
+    let origin = Vec3A::new(0.1, 0.2, 3.0);
+    for i in 0..400 {
+        let target = Vec3A::new(
+            (i % 20) as f32 / 10.0 - 1.0,
+            (i / 20) as f32 / 10.0 - 1.0,
+            0.0,
+        );
+        let ray = Ray::new(origin, target - origin);
+        let brute = triangles
+            .iter()
+            .filter_map(|t| t.intersect(&ray))
+            .map(|(t, _, _)| t)
+            .filter(|&t| t > EPSILON)
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(
+            b.intersect(&mesh, &ray, f32::INFINITY)
+                .map_or(f32::INFINITY, |h| h.t),
+            brute
+        );
+    }
+}
+
+#[test]
+fn test_slab_entry() {
+    let (min, max) = (Vec3A::ZERO, Vec3A::ONE);
+    let entry = |origin: Vec3A, dir: Vec3A| {
+        let ray = Ray::new(origin, dir);
+        slab_entry(min, max, &ray, Vec3A::ONE / dir, f32::INFINITY)
+    };
+    assert_eq!(entry(Vec3A::new(-1.0, 0.5, 0.5), Vec3A::X), Some(1.0));
+    assert_eq!(entry(Vec3A::new(-1.0, 2.0, 0.5), Vec3A::X), None);
+    // zero direction components with the origin on a face of the box give 0 * ∞
+    assert_eq!(entry(Vec3A::new(-1.0, 0.0, 0.5), Vec3A::X), Some(1.0));
+    assert_eq!(entry(Vec3A::new(-1.0, 1.0, 1.0), Vec3A::X), Some(1.0));
+    assert_eq!(entry(Vec3A::new(0.0, 0.5, 2.0), -Vec3A::Z), Some(1.0));
+}
+
+// Slab-Test der Box [min, max], inv_dir = 1 / ray.dir. Liefert den Abstand, ab dem der Strahl in der Box verläuft,
+// falls er sie vor t_max trifft.
+pub fn slab_entry(min: Vec3A, max: Vec3A, ray: &Ray, inv_dir: Vec3A, t_max: f32) -> Option<f32> {
+    let t0 = (min - ray.origin) * inv_dir;
+    let t1 = (max - ray.origin) * inv_dir;
+    // 0 * ∞ for an origin in the plane of a face and a direction parallel to it, such a ray stays in the slab
+    let t0 = Vec3A::select(t0.is_nan_mask(), Vec3A::splat(f32::NEG_INFINITY), t0);
+    let t1 = Vec3A::select(t1.is_nan_mask(), Vec3A::splat(f32::INFINITY), t1);
+    let near = t0.min(t1).max_element().max(0.0);
+    let far = t0.max(t1).min_element().min(t_max);
+    if near <= far {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct BoundingBox {
     //distance: f32,
     //unterteilung: i32,
     // min enthält die minimalen - , max die maximalen x,y und z - Werte für die eine Mitgliedschaft in der Box gilt
     min: Vec3A,
     max: Vec3A,
-    // Alle Dreiecke, die (teilweise) in der Box enthalten sind
-    triangles: Vec<Triangle>,
+    // Indizes aller Dreiecke, die (teilweise) in der Box enthalten sind, nur in Blättern belegt
+    triangles: Vec<usize>,
     children: Vec<BoundingBox>,
 }
 
 impl BoundingBox {
-    pub fn new() -> Self {
-        Self {
-            min: Vec3A::new(0.0, 0.0, 0.0),
-            max: Vec3A::new(0.0, 0.0, 0.0),
-            triangles: Vec::new(),
-            children: Vec::new(),
-        }
-    }
-
     // Spannt eine Bounding Box um eine Menge von Dreiecken auf
     pub fn around(triangles: &mut dyn Iterator<Item = Triangle>) -> Self {
-        let mut min = Vec3A::new(INFINITY, INFINITY, INFINITY);
-        let mut max = Vec3A::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY);
+        let mut min = Vec3A::splat(f32::INFINITY);
+        let mut max = Vec3A::splat(f32::NEG_INFINITY);
         for triangle in triangles {
             for point in triangle.p {
                 min = min.min(point);
@@ -59,4 +149,93 @@ impl BoundingBox {
             children: Vec::new(),
         }
     }
+
+    // Baut den Raumteilungsbaum: jede Box wird bis zur Tiefe unterteilung in 8 gleich große Kindboxen zerlegt
+    pub fn build(mesh: &Mesh, unterteilung: u32) -> Self {
+        let mut root = Self::around(&mut mesh.triangles());
+        root.triangles = (0..mesh.len()).collect();
+        root.subdivide(mesh, unterteilung);
+        root
+    }
+
+    fn overlaps(&self, p: &[Vec3A; 3]) -> bool {
+        let min = p[0].min(p[1]).min(p[2]);
+        let max = p[0].max(p[1]).max(p[2]);
+        min.cmple(self.max).all() && max.cmpge(self.min).all()
+    }
+
+    fn subdivide(&mut self, mesh: &Mesh, depth: u32) {
+        if depth == 0 || self.triangles.len() < SPLIT_TRIANGLES_MIN {
+            return;
+        }
+        let center = (self.min + self.max) / 2.0;
+        for octant in 0..8 {
+            let upper = Vec3A::new(
+                (octant & 1) as f32,
+                ((octant >> 1) & 1) as f32,
+                ((octant >> 2) & 1) as f32,
+            );
+            let mut child = Self {
+                min: self.min + upper * (center - self.min),
+                max: center + upper * (self.max - center),
+                triangles: Vec::new(),
+                children: Vec::new(),
+            };
+            child.triangles = self
+                .triangles
+                .iter()
+                .copied()
+                .filter(|&i| child.overlaps(&mesh.points(i)))
+                .collect();
+            if !child.triangles.is_empty() {
+                child.subdivide(mesh, depth - 1);
+                self.children.push(child);
+            }
+        }
+        self.triangles = Vec::new();
+    }
+
+    // Abstand, ab dem der Strahl in der Box verläuft, falls er sie vor t_max trifft
+    fn entry(&self, ray: &Ray, t_max: f32) -> Option<f32> {
+        slab_entry(self.min, self.max, ray, Vec3A::ONE / ray.dir, t_max)
+    }
+
+    // Nächster Schnittpunkt mit Abstand in (EPSILON, t_max), mesh muss das Netz sein, mit dem der Baum gebaut wurde
+    pub fn intersect(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit> {
+        self.entry(ray, t_max)?;
+        self.intersect_node(mesh, ray, t_max)
+    }
+
+    fn intersect_node(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit> {
+        let mut nearest: Option<Hit> = None;
+        for &i in &self.triangles {
+            if let Some((t, u, v)) = mesh.intersect(i, ray) {
+                if t > EPSILON && t < nearest.map_or(t_max, |h| h.t) {
+                    nearest = Some(Hit {
+                        t,
+                        u,
+                        v,
+                        triangle: i,
+                    });
+                }
+            }
+        }
+        // visit the children front to back, so that farther boxes can be skipped once something was hit
+        let mut children: Vec<(f32, &BoundingBox)> = self
+            .children
+            .iter()
+            .filter_map(|c| c.entry(ray, t_max).map(|t| (t, c)))
+            .collect();
+        children.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (entry, child) in children {
+            let limit = nearest.map_or(t_max, |h| h.t);
+            if entry > limit {
+                break;
+            }
+            if let Some(hit) = child.intersect_node(mesh, ray, limit) {
+                nearest = Some(hit);
+            }
+        }
+        nearest
+    }
 }
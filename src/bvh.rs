@@ -0,0 +1,264 @@
+// Hüllkörperhierarchie (BVH), aufgebaut nach der Surface Area Heuristic und als flaches Array gespeichert
+
+use crate::bounding_box::slab_entry;
+use crate::ray::{Hit, Ray, EPSILON};
+use crate::mesh::Mesh;
+use glam::f32::Vec3A;
+
+// number of buckets the centroids are sorted into when searching for the cheapest split
+const BINS: usize = 12;
+// leaves with at most this many triangles are not split if splitting does not pay off
+const MAX_LEAF_TRIANGLES: usize = 8;
+// cost of visiting a node relative to a triangle intersection test
+const TRAVERSAL_COST: f32 = 1.0;
+
+#[test]
+fn test_build() {
+    let mesh = Mesh::from_triangles(&crate::bounding_box::random_triangles(500));
+    let bvh = Bvh::build(&mesh);
+    // every triangle is referenced exactly once
+    let mut indices = bvh.indices.clone();
+    indices.sort_unstable();
+    assert_eq!(indices, (0..500).collect::<Vec<usize>>());
+    assert!(bvh.nodes.len() > 1);
+    // children lie inside their parents
+    for (i, node) in bvh.nodes.iter().enumerate().filter(|(_, n)| n.count == 0) {
+        for child in [&bvh.nodes[i + 1], &bvh.nodes[node.offset as usize]] {
+            assert!(child.min.cmpge(node.min).all() && child.max.cmple(node.max).all());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Aabb {
+    min: Vec3A,
+    max: Vec3A,
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Self {
+            min: Vec3A::splat(f32::INFINITY),
+            max: Vec3A::splat(f32::NEG_INFINITY),
+        }
+    }
+
+    fn grow(&mut self, other: &Aabb) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    fn grow_point(&mut self, p: Vec3A) {
+        self.min = self.min.min(p);
+        self.max = self.max.max(p);
+    }
+
+    fn area(&self) -> f32 {
+        let d = (self.max - self.min).max(Vec3A::ZERO);
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+}
+
+// Dreieck während des Aufbaus
+#[derive(Clone, Copy)]
+struct Item {
+    triangle: usize,
+    bounds: Aabb,
+    centroid: Vec3A,
+}
+
+// Knoten in Tiefensuche-Reihenfolge, der erste Kindknoten folgt direkt auf seinen Elternknoten
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    min: Vec3A,
+    max: Vec3A,
+    // leaf: first entry in Bvh::indices, inner node: index of the second child
+    offset: u32,
+    // number of triangles of a leaf, 0 for inner nodes
+    count: u32,
+    // axis along which the children were split
+    axis: u32,
+}
+
+// Fach, in das ein Schwerpunkt entlang axis innerhalb der Schwerpunkt-Box fällt
+fn bin_of(centroids: &Aabb, axis: usize, c: Vec3A) -> usize {
+    let extent = centroids.max[axis] - centroids.min[axis];
+    let b = ((c[axis] - centroids.min[axis]) / extent * BINS as f32) as usize;
+    b.min(BINS - 1)
+}
+
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    // triangle indices, each leaf references a contiguous range
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn build(mesh: &Mesh) -> Self {
+        let mut items: Vec<Item> = (0..mesh.len())
+            .map(|i| {
+                let p = mesh.points(i);
+                let mut bounds = Aabb::empty();
+                p.iter().for_each(|&p| bounds.grow_point(p));
+                Item {
+                    triangle: i,
+                    bounds,
+                    centroid: (p[0] + p[1] + p[2]) / 3.0,
+                }
+            })
+            .collect();
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * mesh.len()),
+            indices: Vec::new(),
+        };
+        if !mesh.is_empty() {
+            bvh.build_node(&mut items, 0);
+        }
+        bvh.indices = items.iter().map(|i| i.triangle).collect();
+        bvh
+    }
+
+    // Baut den Knoten für items, start ist die Position von items[0] im gesamten Array
+    fn build_node(&mut self, items: &mut [Item], start: usize) -> usize {
+        let mut bounds = Aabb::empty();
+        let mut centroids = Aabb::empty();
+        for item in items.iter() {
+            bounds.grow(&item.bounds);
+            centroids.grow_point(item.centroid);
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            min: bounds.min,
+            max: bounds.max,
+            offset: start as u32,
+            count: items.len() as u32,
+            axis: 0,
+        });
+        let (axis, bin) = match self.find_split(items, &bounds, &centroids) {
+            Some(split) => split,
+            None => return index,
+        };
+        let mut mid = 0;
+        for i in 0..items.len() {
+            if bin_of(&centroids, axis, items[i].centroid) < bin {
+                items.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == items.len() {
+            return index;
+        }
+        let (left, right) = items.split_at_mut(mid);
+        self.build_node(left, start);
+        let second = self.build_node(right, start + mid);
+        let node = &mut self.nodes[index];
+        node.offset = second as u32;
+        node.count = 0;
+        node.axis = axis as u32;
+        index
+    }
+
+    // Günstigste Teilung nach der SAH als (Achse, erstes Fach rechts der Teilung), None wenn ein Blatt billiger ist
+    fn find_split(
+        &self,
+        items: &[Item],
+        bounds: &Aabb,
+        centroids: &Aabb,
+    ) -> Option<(usize, usize)> {
+        if items.len() <= 1 {
+            return None;
+        }
+        let extent = centroids.max - centroids.min;
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let mut counts = [0usize; BINS];
+            let mut boxes = [Aabb::empty(); BINS];
+            for item in items {
+                let b = bin_of(centroids, axis, item.centroid);
+                counts[b] += 1;
+                boxes[b].grow(&item.bounds);
+            }
+            // sweep from the right to know the cost of everything right of a split
+            let mut right_area = [0.0f32; BINS];
+            let mut right_count = [0usize; BINS];
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for b in (1..BINS).rev() {
+                acc.grow(&boxes[b]);
+                n += counts[b];
+                right_area[b] = acc.area();
+                right_count[b] = n;
+            }
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for b in 1..BINS {
+                acc.grow(&boxes[b - 1]);
+                n += counts[b - 1];
+                if n == 0 || right_count[b] == 0 {
+                    continue;
+                }
+                let cost = n as f32 * acc.area() + right_count[b] as f32 * right_area[b];
+                if best.is_none_or(|(c, _, _)| cost < c) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+        let (cost, axis, bin) = best?;
+        let split_cost = TRAVERSAL_COST + cost / bounds.area();
+        let leaf_cost = items.len() as f32;
+        if split_cost >= leaf_cost && items.len() <= MAX_LEAF_TRIANGLES {
+            return None;
+        }
+        Some((axis, bin))
+    }
+
+    fn entry(node: &Node, ray: &Ray, inv_dir: Vec3A, t_max: f32) -> Option<f32> {
+        slab_entry(node.min, node.max, ray, inv_dir, t_max)
+    }
+
+    // Nächster Schnittpunkt mit Abstand in (EPSILON, t_max), mesh muss das Netz sein, mit dem die BVH gebaut wurde
+    pub fn intersect(&self, mesh: &Mesh, ray: &Ray, t_max: f32) -> Option<Hit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_dir = Vec3A::ONE / ray.dir;
+        let mut nearest: Option<Hit> = None;
+        let mut limit = t_max;
+        let mut stack: Vec<usize> = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            if Self::entry(node, ray, inv_dir, limit).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                let first = node.offset as usize;
+                for &ti in &self.indices[first..first + node.count as usize] {
+                    if let Some((t, u, v)) = mesh.intersect(ti, ray) {
+                        if t > EPSILON && t < limit {
+                            limit = t;
+                            nearest = Some(Hit {
+                                t,
+                                u,
+                                v,
+                                triangle: ti,
+                            });
+                        }
+                    }
+                }
+            } else if ray.dir[node.axis as usize] < 0.0 {
+                // push the far child first so that the near one is visited first
+                stack.push(i + 1);
+                stack.push(node.offset as usize);
+            } else {
+                stack.push(node.offset as usize);
+                stack.push(i + 1);
+            }
+        }
+        nearest
+    }
+}
@@ -1,14 +1,32 @@
 /* Kameraeinstellungen (Position, Orientierung, Field of View) */
 
+use crate::ray::Ray;
 use glam::f32::Vec3A;
 
+#[test]
+fn test_primary_ray() {
+    let c = Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::new(0.0, 0.0, 0.0));
+    // the center of the image looks straight at the target
+    let center = c.primary_ray(50.0, 25.0, 100, 50);
+    assert_eq!(center.origin, c.pos);
+    assert!((center.dir - c.getSichtVektor()).length() < 1e-6);
+    // the top edge of the image is tilted upwards by half the vertical field of view
+    let top = c.primary_ray(50.0, 0.0, 100, 50);
+    assert!((top.dir.dot(c.getSichtVektor()).acos() - c.fovy / 2.0).abs() < 1e-5);
+    assert!(top.dir.y > 0.0);
+    let right = c.primary_ray(100.0, 25.0, 100, 50);
+    assert!(right.dir.dot(c.getRechts()) > 0.0);
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, PartialEq)]
 pub struct Camera {
     // Alles was näher an der Kamera ist wird geclippt
     pub clippingNear: f32,
     // Alles was weiter weg von der Kamera ist wird geclippt
     pub clippingFar: f32,
     // Winkel in Bogenmaß, der den Sichtwinkel in Y - Richtung angibt. Muss zwischen 0 und pi liegen.
-    //fovy: f32,
+    pub fovy: f32,
     // Winkel in Bogenmaß, der den Sichtwinkel in X - Richtung angibt. Muss zwischen 0 und pi liegen.
     //fovx: f32,
     // position of the camera
@@ -24,6 +42,7 @@ impl Camera {
         Self {
             clippingNear: 0.01,
             clippingFar: 1000.0,
+            fovy: 45f32.to_radians(),
             pos,
             target,
             up: Vec3A::new(0.0, 1.0, 0.0),
@@ -31,14 +50,31 @@ impl Camera {
     }
 
     // liefert normierten Vektor zurück der von der Kameraposition zum Kameratarget zeigt
+    #[allow(non_snake_case)]
     pub fn getSichtVektor(&self) -> Vec3A {
         let v: Vec3A = self.target - self.pos;
-        return v.normalize();
+        v.normalize()
     }
 
     // liefert normierten Vektor zurück der nach rechts zeigt
+    #[allow(non_snake_case)]
     pub fn getRechts(&self) -> Vec3A {
         let v: Vec3A = self.up.cross(self.getSichtVektor());
-        return v.normalize();
+        v.normalize()
+    }
+
+    // liefert normierten Vektor zurück der in der Bildebene nach oben zeigt
+    pub fn oben(&self) -> Vec3A {
+        self.getSichtVektor().cross(self.getRechts())
+    }
+
+    // Primärstrahl durch die Bildkoordinate (x, y) eines Bildes mit width x height Pixeln, (0, 0) ist die linke obere Ecke
+    pub fn primary_ray(&self, x: f32, y: f32, width: u32, height: u32) -> Ray {
+        let half_height = (self.fovy / 2.0).tan();
+        let half_width = half_height * width as f32 / height as f32;
+        let sx = (2.0 * x / width as f32 - 1.0) * half_width;
+        let sy = (1.0 - 2.0 * y / height as f32) * half_height;
+        let dir = self.getSichtVektor() + sx * self.getRechts() + sy * self.oben();
+        Ray::new(self.pos, dir)
     }
 }
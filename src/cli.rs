@@ -0,0 +1,269 @@
+use crate::accelerator::AcceleratorKind;
+use crate::image;
+use crate::mesh_processing::Weighting;
+use crate::render::{Integrator, Settings};
+use crate::sampling::{Filter, Pattern};
+use anyhow::anyhow;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: raytracer [OPTIONS] [SCENE]
+
+Arguments:
+  [SCENE]                  scene file [default: scene/abgabeszene.xml]
+
+Options:
+  -o, --output <FILE>      render on the CPU into a .png or .ppm file instead of opening the preview
+  -p, --preview            show the interactive OpenGL preview [default without --output, --export and --save-scene]
+  -m, --mesh <FILE>        triangulation in .xml, .obj, .ply, .stl, .gltf or .glb format, overrides <triangulation> of the scene
+  -r, --resolution <WxH>   image size, overrides <fenster> of the scene
+  -i, --integrator <I>     whitted or path for path tracing with indirect light [default: whitted]
+  -d, --depth <N>          maximum recursion depth for reflection and refraction, with path tracing the
+                           number of bounces before Russian roulette [default: 5]
+  -s, --samples <N>        samples per pixel [default: 1]
+      --pattern <P>        stratified, halton or sobol sample positions [default: stratified]
+      --filter <F>         box, tent, gaussian or mitchell pixel filter [default: box]
+      --seed <N>           seed of the sample positions, renders with the same seed are identical [default: 0]
+      --adaptive <NOISE>   keep sampling in batches of --samples while a pixel's brightness has a larger standard error
+      --max-samples <N>    sample budget per pixel of --adaptive [default: 64]
+      --sample-map <FILE>  also save the number of samples per pixel as a grey image, white is --max-samples
+  -t, --threads <N>        number of render threads [default: number of CPUs]
+  -a, --accelerator <A>    brute, octree (subdivided by <raumteilung>) or bvh [default: octree]
+  -n, --normals <DEGREES>  recompute smooth vertex normals, edges sharper than DEGREES stay creased
+      --normal-weighting <W>
+                           weight the face normals of -n by the angle at the vertex or by the area [default: angle]
+  -e, --export <FILE>      save the triangulation as .xml, .obj with .mtl or .ply
+      --save-scene <FILE>  save the scene as .xml and its triangulation beside it as <NAME>_triangulation.xml
+  -h, --help               print this help";
+
+#[derive(Debug, PartialEq)]
+pub enum Mode {
+    Preview,
+    Render(PathBuf),
+    // only --export or --save-scene was requested
+    None,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub scene: PathBuf,
+    pub triangulation: Option<PathBuf>,
+    pub mode: Mode,
+    pub resolution: Option<(u32, u32)>,
+    pub integrator: Integrator,
+    pub max_depth: u32,
+    pub samples: u32,
+    pub pattern: Pattern,
+    pub filter: Filter,
+    pub seed: u64,
+    pub threshold: Option<f32>,
+    pub max_samples: u32,
+    pub sample_map: Option<PathBuf>,
+    pub threads: usize,
+    pub accelerator: AcceleratorKind,
+    // crease angle in radians if the vertex normals are to be recomputed
+    pub crease_angle: Option<f32>,
+    pub normal_weighting: Weighting,
+    pub export: Option<PathBuf>,
+    pub save_scene: Option<PathBuf>,
+}
+
+impl Options {
+    // Rendereinstellungen, die Bildgröße kommt aus der Szene falls nicht auf der Kommandozeile angegeben
+    pub fn settings(&self, width: u32, height: u32) -> Settings {
+        let (width, height) = self.resolution.unwrap_or((width, height));
+        Settings {
+            width,
+            height,
+            integrator: self.integrator,
+            max_depth: self.max_depth,
+            samples: self.samples,
+            pattern: self.pattern,
+            filter: self.filter,
+            seed: self.seed,
+            threshold: self.threshold,
+            max_samples: self.max_samples,
+            threads: self.threads,
+        }
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let args = |s: &str| parse_args(s.split_whitespace().map(String::from));
+    let o = args("").unwrap().unwrap();
+    assert_eq!(o.scene, PathBuf::from("scene/abgabeszene.xml"));
+    assert_eq!(o.mode, Mode::Preview);
+    assert_eq!(o.resolution, None);
+    assert_eq!(o.triangulation, None);
+    assert_eq!(o.accelerator, AcceleratorKind::BoundingBox);
+    assert_eq!(o.crease_angle, None);
+    assert_eq!(o.export, None);
+    let o = args("-e kopie.obj").unwrap().unwrap();
+    assert_eq!(o.export, Some(PathBuf::from("kopie.obj")));
+    assert_eq!(o.mode, Mode::None);
+    assert_eq!(
+        args("-e kopie.obj -p").unwrap().unwrap().mode,
+        Mode::Preview
+    );
+    let o = args("--save-scene kopie.xml").unwrap().unwrap();
+    assert_eq!(o.save_scene, Some(PathBuf::from("kopie.xml")));
+    assert_eq!(o.mode, Mode::None);
+    assert_eq!(
+        args("-n 180").unwrap().unwrap().crease_angle,
+        Some(std::f32::consts::PI)
+    );
+    assert!(args("-n 200").is_err());
+    let o = args("-n 60 --normal-weighting area").unwrap().unwrap();
+    assert_eq!(o.normal_weighting, Weighting::Area);
+    assert!(args("--normal-weighting mean").is_err());
+    assert_eq!(
+        args("-a bvh").unwrap().unwrap().accelerator,
+        AcceleratorKind::Bvh
+    );
+    let o = args("my.xml -o out.png -r 640x480 --depth 3 -s 16 --threads 2 -m cube.obj")
+        .unwrap()
+        .unwrap();
+    assert_eq!(o.scene, PathBuf::from("my.xml"));
+    assert_eq!(o.mode, Mode::Render(PathBuf::from("out.png")));
+    assert_eq!(o.resolution, Some((640, 480)));
+    assert_eq!(o.triangulation, Some(PathBuf::from("cube.obj")));
+    assert_eq!((o.max_depth, o.samples, o.threads), (3, 16, 2));
+    let o = args("-s 9 --pattern sobol --filter mitchell --seed 7").unwrap().unwrap();
+    assert_eq!((o.pattern, o.filter, o.seed), (Pattern::Sobol, Filter::Mitchell, 7));
+    assert!(args("--filter sinc").is_err());
+    assert_eq!(
+        args("-i path").unwrap().unwrap().integrator,
+        Integrator::Path
+    );
+    assert!(args("-i radiosity").is_err());
+    assert!(args("--seed -1").is_err());
+    let o = args("-o a.png --adaptive 0.02 --max-samples 256 --sample-map n.png")
+        .unwrap()
+        .unwrap();
+    assert_eq!((o.threshold, o.max_samples), (Some(0.02), 256));
+    assert_eq!(o.sample_map, Some(PathBuf::from("n.png")));
+    assert!(args("--sample-map n.png").is_err());
+    assert!(args("-o out.jpg").is_err());
+    assert!(args("-o out.png --sample-map n.jpeg").is_err());
+    assert!(args("--adaptive 0").is_err());
+    assert!(args("--help").unwrap().is_none());
+    assert!(args("-o out.png --preview").is_err());
+    assert!(args("-r 640").is_err());
+    assert!(args("-s 0").is_err());
+    assert!(args("--bogus").is_err());
+    assert!(args("a.xml b.xml").is_err());
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, anyhow::Error> {
+    value
+        .parse::<T>()
+        .map_err(|_| anyhow!("Invalid value for {}: {}", option, value))
+}
+
+fn parse_positive(option: &str, value: &str) -> Result<u32, anyhow::Error> {
+    match parse_number::<u32>(option, value)? {
+        0 => Err(anyhow!("{} must be at least 1", option)),
+        n => Ok(n),
+    }
+}
+
+fn parse_image_path(value: &str) -> Result<PathBuf, anyhow::Error> {
+    let path = PathBuf::from(value);
+    image::format(&path)?;
+    Ok(path)
+}
+
+fn parse_resolution(value: &str) -> Result<(u32, u32), anyhow::Error> {
+    let (w, h) = value.split_once('x').ok_or(anyhow!(
+        "Resolution must be given as WIDTHxHEIGHT: {}",
+        value
+    ))?;
+    Ok((
+        parse_positive("--resolution", w)?,
+        parse_positive("--resolution", h)?,
+    ))
+}
+
+// Wertet die Kommandozeilenargumente (ohne Programmnamen) aus, None bedeutet, dass die Hilfe angefordert wurde
+pub fn parse_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<Option<Options>, anyhow::Error> {
+    let mut scene: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut preview = false;
+    let mut options = Options {
+        scene: PathBuf::new(),
+        triangulation: None,
+        mode: Mode::Preview,
+        resolution: None,
+        integrator: Integrator::Whitted,
+        max_depth: 5,
+        samples: 1,
+        pattern: Pattern::Stratified,
+        filter: Filter::Box,
+        seed: 0,
+        threshold: None,
+        max_samples: 64,
+        sample_map: None,
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        accelerator: AcceleratorKind::BoundingBox,
+        crease_angle: None,
+        normal_weighting: Weighting::Angle,
+        export: None,
+        save_scene: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(anyhow!("Missing value for option {}", arg))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-n" | "--normals" => {
+                let degrees: f32 = parse_number(&arg, &value()?)?;
+                if !(0.0..=180.0).contains(&degrees) {
+                    return Err(anyhow!("{} must be between 0 and 180 degrees", arg));
+                }
+                options.crease_angle = Some(degrees.to_radians());
+            }
+            "--normal-weighting" => options.normal_weighting = value()?.parse()?,
+            "-o" | "--output" => output = Some(parse_image_path(&value()?)?),
+            "-p" | "--preview" => preview = true,
+            "-m" | "--mesh" => options.triangulation = Some(PathBuf::from(value()?)),
+            "-e" | "--export" => options.export = Some(PathBuf::from(value()?)),
+            "--save-scene" => options.save_scene = Some(PathBuf::from(value()?)),
+            "-r" | "--resolution" => options.resolution = Some(parse_resolution(&value()?)?),
+            "-i" | "--integrator" => options.integrator = value()?.parse()?,
+            "-d" | "--depth" => options.max_depth = parse_number(&arg, &value()?)?,
+            "-s" | "--samples" => options.samples = parse_positive(&arg, &value()?)?,
+            "--pattern" => options.pattern = value()?.parse()?,
+            "--filter" => options.filter = value()?.parse()?,
+            "--seed" => options.seed = parse_number(&arg, &value()?)?,
+            "--adaptive" => {
+                let threshold: f32 = parse_number(&arg, &value()?)?;
+                if threshold <= 0.0 {
+                    return Err(anyhow!("{} must be positive", arg));
+                }
+                options.threshold = Some(threshold);
+            }
+            "--max-samples" => options.max_samples = parse_positive(&arg, &value()?)?,
+            "--sample-map" => options.sample_map = Some(parse_image_path(&value()?)?),
+            "-t" | "--threads" => options.threads = parse_positive(&arg, &value()?)? as usize,
+            "-a" | "--accelerator" => options.accelerator = value()?.parse()?,
+            _ if arg.starts_with('-') => return Err(anyhow!("Unknown option: {}", arg)),
+            _ if scene.is_none() => scene = Some(PathBuf::from(&arg)),
+            _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+        }
+    }
+    if options.sample_map.is_some() && output.is_none() {
+        return Err(anyhow!("--sample-map requires --output"));
+    }
+    options.scene = scene.unwrap_or_else(|| PathBuf::from("scene/abgabeszene.xml"));
+    options.mode = match (output, preview) {
+        (Some(_), true) => return Err(anyhow!("--output and --preview exclude each other")),
+        (Some(output), false) => Mode::Render(output),
+        (None, false) if options.export.is_some() || options.save_scene.is_some() => Mode::None,
+        (None, _) => Mode::Preview,
+    };
+    Ok(Some(options))
+}
@@ -1,3 +1,6 @@
+use std::ops::{Add, Mul};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -15,7 +18,38 @@ impl Color {
         }
     }
 
+    pub fn black() -> Self {
+        Self::new(0.0, 0.0, 0.0, 1.0)
+    }
+
     pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
+
+    // relative Helligkeit nach Rec. 709
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+}
+
+// Farbarithmetik wirkt nur auf r, g und b, alpha bleibt beim linken Operanden
+impl Add for Color {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self::new(self.r + o.r, self.g + o.g, self.b + o.b, self.a)
+    }
+}
+
+impl Mul for Color {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        Self::new(self.r * o.r, self.g * o.g, self.b * o.b, self.a)
+    }
+}
+
+impl Mul<f32> for Color {
+    type Output = Self;
+    fn mul(self, f: f32) -> Self {
+        Self::new(self.r * f, self.g * f, self.b * f, self.a)
+    }
 }
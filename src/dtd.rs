@@ -0,0 +1,727 @@
+// Prüft XML-Dokumente gegen eine DTD. Unterstützt werden ELEMENT- und ATTLIST-Deklarationen mit Parameter-Entities,
+// was für szene.dtd und triangulation.dtd genügt.
+
+use anyhow::anyhow;
+use roxmltree::{Document, Node, TextPos};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::LazyLock;
+
+// the DTDs are compiled in, so that validation does not depend on the SYSTEM path of the DOCTYPE
+pub const SZENE_DTD: &str = include_str!("../scene/szene.dtd");
+pub const TRIANGULATION_DTD: &str = include_str!("../scene/triangulation.dtd");
+
+// parsed once on first use, test_parse_dtd makes sure that this does not panic
+pub static SZENE: LazyLock<Dtd> =
+    LazyLock::new(|| Dtd::parse(SZENE_DTD).expect("invalid built-in DTD"));
+pub static TRIANGULATION: LazyLock<Dtd> =
+    LazyLock::new(|| Dtd::parse(TRIANGULATION_DTD).expect("invalid built-in DTD"));
+
+#[cfg(test)]
+fn children(dtd: &Dtd, name: &str) -> Regex {
+    match &dtd.elements[name] {
+        Content::Children(regex) => regex.clone(),
+        content => panic!("{:?}", content),
+    }
+}
+
+#[test]
+fn test_parse_dtd() {
+    let dtd = Dtd::parse(SZENE_DTD).unwrap();
+    let kamera = children(&dtd, "kamera");
+    assert!(!kamera.nullable());
+    let rest = kamera.derive("position").derive("ziel");
+    assert_eq!(rest, Regex::Element("fovy".to_string()));
+    assert!(rest.derive("fovy").nullable());
+    assert_eq!(kamera.derive("ziel"), Regex::Nothing);
+    // position is declared twice with the same attributes, the first declaration counts
+    assert_eq!(dtd.attributes["position"].len(), 3);
+    let dtd = Dtd::parse(TRIANGULATION_DTD).unwrap();
+    assert!(children(&dtd, "triangulation").nullable());
+    assert!(matches!(
+        dtd.attributes["diffus"][3].default,
+        DefaultValue::Implied
+    ));
+    assert!(Dtd::parse("<!ELEMENT a (b,c|d)>").is_err());
+    assert!(Dtd::parse("<!ELEMENT a (b,c) <!ELEMENT c EMPTY>").is_err());
+}
+
+#[test]
+fn test_validate() {
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE triangulation SYSTEM "triangulation.dtd">
+<triangulation>
+<material name="rot" glanzwert="1"><ambient r="1" g="0" b="0"/><diffus r="1" g="0" b="0" a="1"/><spiegelnd r="0" g="0" b="0"/></material>
+<dreieck material="rot"><punkt1 x="0" y="0" z="0"/><punkt2 x="1" y="0" z="0"/><punkt3 x="0" y="1" z="0"/>
+<normale1 x="0" y="0" z="1"/><normale2 x="0" y="0" z="1"/><normale3 x="0" y="0" z="1"/></dreieck>
+</triangulation>"#;
+    let dtd = Dtd::parse(TRIANGULATION_DTD).unwrap();
+    let validate = |xml: &str| {
+        let doc = Document::parse(xml).unwrap();
+        dtd.validate(&doc, "triangulation")
+    };
+    assert!(validate(XML).is_ok());
+    let broken = XML
+        .replace(r#" glanzwert="1""#, r#" glanz="1""#)
+        .replace(r#"<punkt2 x="1" y="0" z="0"/>"#, "")
+        .replace("</dreieck>", "<kante/>text</dreieck>");
+    let errors = validate(&broken).unwrap_err().0;
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "line 4, column 1: <material> is missing the required attribute glanzwert",
+            "line 4, column 22: attribute glanz is not declared for <material>",
+            "line 5, column 52: unexpected <punkt3> in <dreieck>, expected <punkt2>",
+            "line 6, column 88: undeclared element <kante>",
+            "line 6, column 96: text is not allowed in <dreieck>",
+        ]
+    );
+    let errors = validate(r#"<szene><fenster breite="1"/></szene>"#)
+        .unwrap_err()
+        .0;
+    assert_eq!(
+        errors[0].message,
+        "the root element is <szene>, expected <triangulation>"
+    );
+    assert_eq!(errors.len(), 3);
+}
+
+#[test]
+fn test_incomplete() {
+    let dtd = Dtd::parse(SZENE_DTD).unwrap();
+    let doc =
+        Document::parse("<kamera>\n  <position x=\"0\" y=\"0\" z=\"0\"/>\n</kamera>").unwrap();
+    let errors = dtd.validate(&doc, "kamera").unwrap_err().0;
+    assert_eq!(
+        errors[0].to_string(),
+        "line 3, column 1: <kamera> ends before <ziel>"
+    );
+    let error = ValidationError::from_xml(&Document::parse("<a>\n<b></a>").unwrap_err());
+    assert_eq!((error.line, error.column), (2, 4));
+    assert_eq!(error.message, "expected 'b' tag, not 'a'");
+}
+
+// Ein Verstoß gegen die DTD oder ein Syntaxfehler, Zeilen und Spalten beginnen bei 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn at(pos: TextPos, message: String) -> Self {
+        Self {
+            line: pos.row,
+            column: pos.col,
+            message,
+        }
+    }
+
+    pub fn from_xml(e: &roxmltree::Error) -> Self {
+        let message = e.to_string();
+        // roxmltree appends the position, which is kept separately here
+        let suffix = format!(" at {}", e.pos());
+        let message = message
+            .strip_suffix(&suffix)
+            .unwrap_or(&message)
+            .to_string();
+        Self::at(e.pos(), message)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+// Alle Probleme eines Dokuments, eines pro Zeile
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.0.iter().map(|e| e.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+// Inhaltsmodell eines Elements als regulärer Ausdruck über die Namen der Kindelemente
+#[derive(Debug, Clone, PartialEq)]
+enum Regex {
+    // matches nothing, not even the empty sequence
+    Nothing,
+    Epsilon,
+    Element(String),
+    Seq(Box<Regex>, Box<Regex>),
+    Alt(Box<Regex>, Box<Regex>),
+    Star(Box<Regex>),
+}
+
+impl Regex {
+    // simplifying constructors keep derivatives of long child lists small
+    fn seq(a: Regex, b: Regex) -> Regex {
+        match (a, b) {
+            (Regex::Nothing, _) | (_, Regex::Nothing) => Regex::Nothing,
+            (Regex::Epsilon, r) | (r, Regex::Epsilon) => r,
+            (a, b) => Regex::Seq(Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn alt(a: Regex, b: Regex) -> Regex {
+        match (a, b) {
+            (Regex::Nothing, r) | (r, Regex::Nothing) => r,
+            (a, b) if a == b => a,
+            (a, b) => Regex::Alt(Box::new(a), Box::new(b)),
+        }
+    }
+
+    // whether the element may end here
+    fn nullable(&self) -> bool {
+        match self {
+            Regex::Nothing | Regex::Element(_) => false,
+            Regex::Epsilon | Regex::Star(_) => true,
+            Regex::Seq(a, b) => a.nullable() && b.nullable(),
+            Regex::Alt(a, b) => a.nullable() || b.nullable(),
+        }
+    }
+
+    // Brzozowski-Ableitung: was nach einem Kindelement name noch folgen darf
+    fn derive(&self, name: &str) -> Regex {
+        match self {
+            Regex::Nothing | Regex::Epsilon => Regex::Nothing,
+            Regex::Element(n) if n == name => Regex::Epsilon,
+            Regex::Element(_) => Regex::Nothing,
+            Regex::Seq(a, b) => {
+                let first = Regex::seq(a.derive(name), (**b).clone());
+                if a.nullable() {
+                    Regex::alt(first, b.derive(name))
+                } else {
+                    first
+                }
+            }
+            Regex::Alt(a, b) => Regex::alt(a.derive(name), b.derive(name)),
+            Regex::Star(a) => Regex::seq(a.derive(name), self.clone()),
+        }
+    }
+
+    // names of the elements that may come next
+    fn first(&self, names: &mut BTreeSet<String>) {
+        match self {
+            Regex::Nothing | Regex::Epsilon => {}
+            Regex::Element(n) => {
+                names.insert(n.clone());
+            }
+            Regex::Seq(a, b) => {
+                a.first(names);
+                if a.nullable() {
+                    b.first(names);
+                }
+            }
+            Regex::Alt(a, b) => {
+                a.first(names);
+                b.first(names);
+            }
+            Regex::Star(a) => a.first(names),
+        }
+    }
+
+    fn expected(&self) -> String {
+        let mut names = BTreeSet::new();
+        self.first(&mut names);
+        let names: Vec<String> = names.iter().map(|n| format!("<{}>", n)).collect();
+        names.join(" or ")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Content {
+    Empty,
+    Any,
+    // #PCDATA, optionally mixed with the given elements
+    Mixed(Vec<String>),
+    Children(Regex),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DefaultValue {
+    Required,
+    Implied,
+    Fixed(String),
+    Value(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AttributeDecl {
+    name: String,
+    // allowed values of an enumerated type, None for CDATA and the other string types
+    values: Option<Vec<String>>,
+    default: DefaultValue,
+}
+
+#[derive(Debug, Default)]
+pub struct Dtd {
+    elements: HashMap<String, Content>,
+    attributes: HashMap<String, Vec<AttributeDecl>>,
+}
+
+// Inhalte von <!...>-Deklarationen ohne Kommentare und Processing Instructions
+fn declarations(dtd: &str) -> Result<Vec<&str>, anyhow::Error> {
+    let mut result = Vec::new();
+    let mut rest = dtd.trim_start();
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").ok_or(anyhow!("Unterminated comment"))?;
+            rest = &comment[end + 3..];
+        } else if let Some(pi) = rest.strip_prefix("<?") {
+            let end = pi
+                .find("?>")
+                .ok_or(anyhow!("Unterminated processing instruction"))?;
+            rest = &pi[end + 2..];
+        } else if let Some(decl) = rest.strip_prefix("<!") {
+            // '>' may appear inside quoted entity values
+            let mut quote: Option<char> = None;
+            let end = decl
+                .char_indices()
+                .find(|&(_, c)| match quote {
+                    Some(q) => {
+                        if c == q {
+                            quote = None;
+                        }
+                        false
+                    }
+                    None if c == '"' || c == '\'' => {
+                        quote = Some(c);
+                        false
+                    }
+                    None => c == '<' || c == '>',
+                })
+                .filter(|&(_, c)| c == '>')
+                .ok_or(anyhow!(
+                    "Unterminated declaration: <!{}",
+                    decl.lines().next().unwrap_or("")
+                ))?
+                .0;
+            result.push(&decl[..end]);
+            rest = &decl[end + 1..];
+        } else {
+            return Err(anyhow!(
+                "Unexpected text in DTD: {}",
+                rest.lines().next().unwrap_or("")
+            ));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(result)
+}
+
+// Zerlegt eine Deklaration in Namen, Satzzeichen und Zeichenketten in Anführungszeichen (ohne diese)
+fn tokens(decl: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = decl.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {}
+            '(' | ')' | '|' | ',' | '?' | '*' | '+' => tokens.push(c.to_string()),
+            '"' | '\'' => {
+                let value: String = chars.by_ref().take_while(|&d| d != c).collect();
+                tokens.push(format!("\"{}", value));
+            }
+            _ => {
+                let mut name = c.to_string();
+                while let Some(&d) = chars.peek() {
+                    if d.is_whitespace() || "()|,?*+\"'".contains(d) {
+                        break;
+                    }
+                    name.push(d);
+                    chars.next();
+                }
+                tokens.push(name);
+            }
+        }
+    }
+    tokens
+}
+
+// Ersetzt %name; durch den Wert des Parameter-Entities
+fn expand(decl: &str, entities: &HashMap<String, String>) -> Result<String, anyhow::Error> {
+    let mut result = decl.to_string();
+    // entity values may reference other entities
+    for _ in 0..16 {
+        // "% name" without a semicolon declares a parameter entity
+        let reference = result
+            .match_indices('%')
+            .map(|(i, _)| i)
+            .find(|&i| result[i + 1..].starts_with(|c: char| c.is_alphabetic() || c == '_'));
+        let start = match reference {
+            Some(start) => start,
+            None => return Ok(result),
+        };
+        let end = result[start..]
+            .find(';')
+            .ok_or(anyhow!("Malformed entity reference in {}", decl))?;
+        let name = &result[start + 1..start + end];
+        let value = entities
+            .get(name)
+            .ok_or(anyhow!("Unknown parameter entity %{};", name))?;
+        result.replace_range(start..start + end + 1, value);
+    }
+    Err(anyhow!("Entity reference loop in {}", decl))
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    i: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.i).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Result<&'a str, anyhow::Error> {
+        let token = self
+            .peek()
+            .ok_or(anyhow!("Unexpected end of declaration"))?;
+        self.i += 1;
+        Ok(token)
+    }
+
+    // Name oder geklammerte Sequenz bzw. Auswahl mit optionalem ?, * oder +
+    fn particle(&mut self) -> Result<Regex, anyhow::Error> {
+        let base = match self.next()? {
+            "(" => {
+                let mut items = vec![self.particle()?];
+                let mut separator: Option<&str> = None;
+                loop {
+                    match self.next()? {
+                        ")" => break,
+                        s @ ("," | "|") if separator.is_none_or(|t| t == s) => {
+                            separator = Some(s);
+                            items.push(self.particle()?);
+                        }
+                        t => return Err(anyhow!("Unexpected {} in content model", t)),
+                    }
+                }
+                let combine = if separator == Some("|") {
+                    Regex::alt
+                } else {
+                    Regex::seq
+                };
+                let last = items.pop().unwrap();
+                items
+                    .into_iter()
+                    .rev()
+                    .fold(last, |r, item| combine(item, r))
+            }
+            name if name
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_-.:".contains(c)) =>
+            {
+                Regex::Element(name.to_string())
+            }
+            t => return Err(anyhow!("Unexpected {} in content model", t)),
+        };
+        Ok(match self.peek() {
+            Some("?") => {
+                self.i += 1;
+                Regex::alt(base, Regex::Epsilon)
+            }
+            Some("*") => {
+                self.i += 1;
+                Regex::Star(Box::new(base))
+            }
+            Some("+") => {
+                self.i += 1;
+                Regex::seq(base.clone(), Regex::Star(Box::new(base)))
+            }
+            _ => base,
+        })
+    }
+
+    fn content(&mut self) -> Result<Content, anyhow::Error> {
+        match self.peek() {
+            Some("EMPTY") => {
+                self.i += 1;
+                return Ok(Content::Empty);
+            }
+            Some("ANY") => {
+                self.i += 1;
+                return Ok(Content::Any);
+            }
+            _ => {}
+        }
+        if self.tokens.get(self.i + 1).map(|t| t.as_str()) == Some("#PCDATA") {
+            self.i += 2;
+            let mut names = Vec::new();
+            while self.next()? == "|" {
+                names.push(self.next()?.to_string());
+            }
+            // (#PCDATA|a|b)* or just (#PCDATA)
+            if self.peek() == Some("*") {
+                self.i += 1;
+            }
+            return Ok(Content::Mixed(names));
+        }
+        Ok(Content::Children(self.particle()?))
+    }
+
+    fn attribute(&mut self) -> Result<AttributeDecl, anyhow::Error> {
+        let name = self.next()?.to_string();
+        let values = match self.next()? {
+            "(" => {
+                let mut values = vec![self.next()?.to_string()];
+                while self.next()? == "|" {
+                    values.push(self.next()?.to_string());
+                }
+                Some(values)
+            }
+            _ => None,
+        };
+        let default = match self.next()? {
+            "#REQUIRED" => DefaultValue::Required,
+            "#IMPLIED" => DefaultValue::Implied,
+            "#FIXED" => DefaultValue::Fixed(self.next()?.trim_start_matches('"').to_string()),
+            v if v.starts_with('"') => DefaultValue::Value(v[1..].to_string()),
+            t => {
+                return Err(anyhow!(
+                    "Invalid default value {} for attribute {}",
+                    t,
+                    name
+                ))
+            }
+        };
+        Ok(AttributeDecl {
+            name,
+            values,
+            default,
+        })
+    }
+}
+
+impl Dtd {
+    pub fn parse(dtd: &str) -> Result<Self, anyhow::Error> {
+        let mut result = Dtd::default();
+        let mut entities: HashMap<String, String> = HashMap::new();
+        for decl in declarations(dtd)? {
+            let decl = expand(decl, &entities)?;
+            let tokens = tokens(&decl);
+            let mut p = Parser {
+                tokens: &tokens,
+                i: 0,
+            };
+            let kind = p.next()?;
+            match kind {
+                "ENTITY" => {
+                    // general entities are not needed for the scene formats
+                    if p.next()? == "%" {
+                        let name = p.next()?.to_string();
+                        let value = p.next()?.trim_start_matches('"').to_string();
+                        entities.entry(name).or_insert(value);
+                    }
+                    continue;
+                }
+                "ELEMENT" => {
+                    let name = p.next()?.to_string();
+                    let content = p.content()?;
+                    if result.elements.insert(name.clone(), content).is_some() {
+                        return Err(anyhow!("Element {} is declared twice", name));
+                    }
+                }
+                "ATTLIST" => {
+                    let element = p.next()?.to_string();
+                    let list = result.attributes.entry(element).or_default();
+                    while p.peek().is_some() {
+                        let attribute = p.attribute()?;
+                        // the first declaration of an attribute is binding
+                        if list.iter().all(|a| a.name != attribute.name) {
+                            list.push(attribute);
+                        }
+                    }
+                }
+                _ => return Err(anyhow!("Unsupported declaration <!{}", kind)),
+            }
+            if p.peek().is_some() {
+                return Err(anyhow!("Unexpected {} in <!{}>", p.next()?, decl.trim()));
+            }
+        }
+        Ok(result)
+    }
+
+    fn check_attributes(&self, doc: &Document, node: &Node, errors: &mut Vec<ValidationError>) {
+        let name = node.tag_name().name();
+        let pos = |start: usize| doc.text_pos_at(start);
+        let declared = self.attributes.get(name).map_or(&[][..], |a| &a[..]);
+        for a in declared {
+            match (node.attribute(a.name.as_str()), &a.default) {
+                (None, DefaultValue::Required) => errors.push(ValidationError::at(
+                    pos(node.range().start),
+                    format!("<{}> is missing the required attribute {}", name, a.name),
+                )),
+                (Some(value), DefaultValue::Fixed(fixed)) if value != fixed => {
+                    errors.push(ValidationError::at(
+                        pos(node.attribute_node(a.name.as_str()).unwrap().range().start),
+                        format!("attribute {} must have the value {}", a.name, fixed),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        for attribute in node.attributes() {
+            let start = || pos(attribute.range().start);
+            match declared.iter().find(|a| a.name == attribute.name()) {
+                None => errors.push(ValidationError::at(
+                    start(),
+                    format!(
+                        "attribute {} is not declared for <{}>",
+                        attribute.name(),
+                        name
+                    ),
+                )),
+                Some(AttributeDecl {
+                    values: Some(values),
+                    ..
+                }) if !values.iter().any(|v| v == attribute.value()) => {
+                    errors.push(ValidationError::at(
+                        start(),
+                        format!(
+                            "invalid value {} for attribute {}, expected one of {}",
+                            attribute.value(),
+                            attribute.name(),
+                            values.join(", ")
+                        ),
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_content(
+        &self,
+        doc: &Document,
+        node: &Node,
+        content: &Content,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let name = node.tag_name().name();
+        let mut state = match content {
+            Content::Children(regex) => Some(regex.clone()),
+            _ => None,
+        };
+        for child in node.children() {
+            // text_pos_at counts from the start of the document, so it is only called for errors
+            let pos = || doc.text_pos_at(child.range().start);
+            if child.is_text() {
+                let text_allowed = matches!(content, Content::Any | Content::Mixed(_));
+                if !text_allowed && !child.text().unwrap_or("").trim().is_empty() {
+                    errors.push(ValidationError::at(
+                        pos(),
+                        format!("text is not allowed in <{}>", name),
+                    ));
+                }
+                continue;
+            }
+            if !child.is_element() {
+                continue;
+            }
+            let child_name = child.tag_name().name();
+            let allowed = match content {
+                Content::Empty => false,
+                Content::Any => true,
+                Content::Mixed(names) => names.iter().any(|n| n == child_name),
+                Content::Children(_) => match state.take() {
+                    Some(regex) => {
+                        let next = regex.derive(child_name);
+                        if next == Regex::Nothing {
+                            // later children are not checked, as they would only report follow-up errors
+                            let mut expected = regex.expected();
+                            if regex.nullable() {
+                                let end = format!("the end of <{}>", name);
+                                expected = if expected.is_empty() {
+                                    end
+                                } else {
+                                    format!("{} or {}", expected, end)
+                                };
+                            }
+                            errors.push(ValidationError::at(
+                                pos(),
+                                format!(
+                                    "unexpected <{}> in <{}>, expected {}",
+                                    child_name, name, expected
+                                ),
+                            ));
+                        } else {
+                            state = Some(next);
+                        }
+                        true
+                    }
+                    None => true,
+                },
+            };
+            if !allowed {
+                errors.push(ValidationError::at(
+                    pos(),
+                    format!("<{}> is not allowed in <{}>", child_name, name),
+                ));
+            }
+        }
+        if let Some(regex) = state {
+            if !regex.nullable() {
+                // position of the end tag
+                let end = node.range().end;
+                let start = doc.input_text()[..end]
+                    .rfind('<')
+                    .unwrap_or(node.range().start);
+                errors.push(ValidationError::at(
+                    doc.text_pos_at(start),
+                    format!("<{}> ends before {}", name, regex.expected()),
+                ));
+            }
+        }
+    }
+
+    // Prüft alle Elemente des Dokuments, root ist der im DOCTYPE angegebene Name des Wurzelelements
+    pub fn validate(&self, doc: &Document, root: &str) -> Result<(), ValidationErrors> {
+        let mut errors = Vec::new();
+        let root_element = doc.root_element();
+        if root_element.tag_name().name() != root {
+            errors.push(ValidationError::at(
+                doc.text_pos_at(root_element.range().start),
+                format!(
+                    "the root element is <{}>, expected <{}>",
+                    root_element.tag_name().name(),
+                    root
+                ),
+            ));
+        }
+        for node in root_element.descendants().filter(|n| n.is_element()) {
+            let name = node.tag_name().name();
+            match self.elements.get(name) {
+                Some(content) => {
+                    self.check_attributes(doc, &node, &mut errors);
+                    self.check_content(doc, &node, content, &mut errors);
+                }
+                None => errors.push(ValidationError::at(
+                    doc.text_pos_at(node.range().start),
+                    format!("undeclared element <{}>", name),
+                )),
+            }
+        }
+        // in document order, content errors are found before those of the children
+        errors.sort_by_key(|e| (e.line, e.column));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// Schreibt Szenen und Triangulierungen im XML-Format von PfannkuchenEdit, Gegenstück zu file_import
+
+use crate::color::Color;
+use crate::light_source::{AreaLight, Light, Shape};
+use crate::material::{Material, BRECHUNGSINDEX_GLAS};
+use crate::mesh::Mesh;
+use crate::obj;
+use crate::ply;
+use crate::scene::Scene;
+use anyhow::{anyhow, Context};
+use glam::f32::Vec3A;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_write_triangulation() {
+    use crate::triangle::Triangle;
+    let mut glas = Material::new("glas & \"luft\"", Color::new(0.1, 0.2, 0.3, 0.5));
+    glas.brechungsindex = 1.33;
+    let materials = vec![Material::new("rot", Color::new(1.0, 0.0, 0.0, 1.0)), glas];
+    let mut t = Triangle::new(Vec3A::ZERO, Vec3A::X, Vec3A::new(0.1, 1e-7, -3.25));
+    t.material = 1;
+    let mesh = Mesh::from_triangles(&[t]);
+    let xml = write_triangulation(&materials, &mesh);
+    assert!(xml.contains(r#"<dreieck material="glas &amp; &quot;luft&quot;">"#));
+    let (parsed_materials, triangles) = crate::file_import::parse_triangulation(&xml).unwrap();
+    assert_eq!(parsed_materials, materials);
+    assert_eq!(Mesh::from_triangles(&triangles), mesh);
+}
+
+#[test]
+fn test_round_trip() {
+    use crate::file_import::{load_scene, parse_scene};
+    use crate::light_source::{DirectionalLight, SpotLight};
+    let mut scene = load_scene(Path::new("scene/abgabeszene.xml"), None).unwrap();
+    let shapes = [
+        Shape::Rectangle {
+            center: Vec3A::new(0.0, 9.0, 0.0),
+            edge1: Vec3A::X,
+            edge2: Vec3A::new(0.0, 0.0, 0.5),
+        },
+        Shape::Disk {
+            center: Vec3A::ZERO,
+            normal: Vec3A::Y,
+            radius: 0.25,
+        },
+        Shape::Sphere {
+            center: Vec3A::ONE,
+            radius: 0.1,
+        },
+        Shape::Triangle([Vec3A::ZERO, Vec3A::X, Vec3A::Y]),
+        Shape::emissive(
+            1,
+            scene
+                .mesh
+                .triangles()
+                .filter(|t| t.material == 1)
+                .map(|t| t.p)
+                .collect(),
+        ),
+    ];
+    for (i, shape) in shapes.into_iter().enumerate() {
+        scene.lights.push(Light::Area(AreaLight {
+            shape,
+            color: Color::new(0.5, 0.25, 1.0, 1.0),
+            samples: i as u32 + 1,
+        }));
+    }
+    scene.lights.push(Light::Directional(DirectionalLight {
+        dir: Vec3A::new(0.3, -1.0, 0.2),
+        color: Color::new(1.0, 0.9, 0.7, 1.0),
+    }));
+    scene.lights.push(Light::Spot(SpotLight {
+        pos: Vec3A::new(0.0, 9.0, 0.0),
+        dir: -Vec3A::Y,
+        color: Color::white(),
+        angle: 25f32.to_radians(),
+        falloff: 1.5,
+    }));
+    let dir = std::env::temp_dir().join(format!("raytracer_export_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("kopie.xml");
+    save_scene(&scene, &path).unwrap();
+    assert!(dir.join("kopie_triangulation.xml").exists());
+    assert_eq!(load_scene(&path, None).unwrap(), scene);
+    let xml = write_scene(&scene, "kopie_triangulation.xml");
+    assert_eq!(parse_scene(&xml, &dir, None).unwrap(), scene);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_triangulation() {
+    use crate::file_import::{load_scene, load_triangulation};
+    let scene = load_scene(Path::new("scene/abgabeszene.xml"), None).unwrap();
+    let dir = std::env::temp_dir().join(format!("raytracer_mesh_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["kopie.xml", "kopie.obj", "kopie.ply"] {
+        let path = dir.join(name);
+        save_triangulation(&path, &scene.materials, &scene.mesh).unwrap();
+        let (_, triangles) = load_triangulation(&path).unwrap();
+        assert_eq!(triangles.len(), scene.mesh.len());
+    }
+    assert!(dir.join("kopie.mtl").exists());
+    assert!(save_triangulation(&dir.join("kopie.stl"), &scene.materials, &scene.mesh).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Ersetzt die in XML-Attributwerten reservierten Zeichen
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// f32 is printed with the shortest representation that parses back to the same value
+fn vector3(xml: &mut String, tag: &str, v: Vec3A) {
+    writeln!(xml, r#"<{} x="{}" y="{}" z="{}"/>"#, tag, v.x, v.y, v.z).unwrap();
+}
+
+// farbe3d of the scene DTD has no alpha channel
+fn rgb(xml: &mut String, tag: &str, c: Color) {
+    writeln!(xml, r#"<{} r="{}" g="{}" b="{}"/>"#, tag, c.r, c.g, c.b).unwrap();
+}
+
+fn rgba(xml: &mut String, tag: &str, c: Color) {
+    writeln!(
+        xml,
+        r#"<{} r="{}" g="{}" b="{}" a="{}"/>"#,
+        tag, c.r, c.g, c.b, c.a
+    )
+    .unwrap();
+}
+
+// Triangulierung mit den Materialien und allen Dreiecken von mesh, mesh.materials sind Indizes in materials
+pub fn write_triangulation(materials: &[Material], mesh: &Mesh) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<!DOCTYPE triangulation SYSTEM \"triangulation.dtd\">\n");
+    xml.push_str("<triangulation>\n");
+    for m in materials {
+        write!(
+            xml,
+            r#"<material glanzwert="{}" name="{}""#,
+            m.glanz,
+            escape(&m.name)
+        )
+        .unwrap();
+        // not part of the PfannkuchenEdit format, so only written when needed
+        if m.brechungsindex != BRECHUNGSINDEX_GLAS {
+            write!(xml, r#" brechungsindex="{}""#, m.brechungsindex).unwrap();
+        }
+        xml.push_str(">\n");
+        rgba(&mut xml, "ambient", m.ambient);
+        rgba(&mut xml, "diffus", m.diffus);
+        rgba(&mut xml, "spiegelnd", m.spiegelnd);
+        xml.push_str("</material>\n");
+    }
+    for t in mesh.triangles() {
+        writeln!(
+            xml,
+            r#"<dreieck material="{}">"#,
+            escape(&materials[t.material].name)
+        )
+        .unwrap();
+        for (i, p) in t.p.iter().enumerate() {
+            vector3(&mut xml, &format!("punkt{}", i + 1), *p);
+        }
+        for (i, n) in t.n.iter().enumerate() {
+            vector3(&mut xml, &format!("normale{}", i + 1), *n);
+        }
+        xml.push_str("</dreieck>\n");
+    }
+    xml.push_str("</triangulation>\n");
+    xml
+}
+
+// <flaechenlicht>, leuchtende Dreiecke der Triangulierung werden über den Namen ihres Materials angegeben
+fn write_area_light(xml: &mut String, scene: &Scene, light: &AreaLight) {
+    writeln!(xml, r#"<flaechenlicht abtastungen="{}">"#, light.samples).unwrap();
+    match &light.shape {
+        Shape::Rectangle {
+            center,
+            edge1,
+            edge2,
+        } => {
+            xml.push_str("<rechteck>\n");
+            vector3(xml, "position", *center);
+            vector3(xml, "kante1", *edge1);
+            vector3(xml, "kante2", *edge2);
+            xml.push_str("</rechteck>\n");
+        }
+        Shape::Disk {
+            center,
+            normal,
+            radius,
+        } => {
+            writeln!(xml, r#"<scheibe radius="{}">"#, radius).unwrap();
+            vector3(xml, "position", *center);
+            vector3(xml, "normale", *normal);
+            xml.push_str("</scheibe>\n");
+        }
+        Shape::Sphere { center, radius } => {
+            writeln!(xml, r#"<kugel radius="{}">"#, radius).unwrap();
+            vector3(xml, "position", *center);
+            xml.push_str("</kugel>\n");
+        }
+        Shape::Triangle(p) => {
+            xml.push_str("<dreieck>\n");
+            for (i, p) in p.iter().enumerate() {
+                vector3(xml, &format!("punkt{}", i + 1), *p);
+            }
+            xml.push_str("</dreieck>\n");
+        }
+        // the triangles are part of the triangulation
+        Shape::Emissive { material, .. } => {
+            writeln!(
+                xml,
+                r#"<material name="{}"/>"#,
+                escape(&scene.materials[*material].name)
+            )
+            .unwrap();
+        }
+    }
+    rgb(xml, "farbe", light.color);
+    xml.push_str("</flaechenlicht>\n");
+}
+
+// Szenendatei nach szene.dtd, die Dreiecke werden mit write_triangulation separat unter triangulation_src abgelegt
+pub fn write_scene(scene: &Scene, triangulation_src: &str) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<!DOCTYPE szene SYSTEM \"szene.dtd\">\n");
+    xml.push_str("<szene>\n");
+    writeln!(
+        xml,
+        r#"<triangulation src="{}"/>"#,
+        escape(triangulation_src)
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        r#"<fenster breite="{}" hoehe="{}"/>"#,
+        scene.width, scene.height
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        r#"<raumteilung unterteilung="{}"/>"#,
+        scene.subdivision
+    )
+    .unwrap();
+    xml.push_str("<kamera>\n");
+    vector3(&mut xml, "position", scene.camera.pos);
+    vector3(&mut xml, "ziel", scene.camera.target);
+    // converted in double precision, so that reading the degrees back yields the same f32
+    writeln!(
+        xml,
+        r#"<fovy winkel="{}"/>"#,
+        (scene.camera.fovy as f64).to_degrees()
+    )
+    .unwrap();
+    xml.push_str("</kamera>\n");
+    xml.push_str("<beleuchtung>\n");
+    rgb(&mut xml, "hintergrundfarbe", scene.background);
+    rgb(&mut xml, "ambientehelligkeit", scene.ambient);
+    let a = &scene.attenuation;
+    writeln!(
+        xml,
+        r#"<abschwaechung konstant="{}" linear="{}" quadratisch="{}"/>"#,
+        a.konstant, a.linear, a.quadratisch
+    )
+    .unwrap();
+    for light in &scene.lights {
+        match light {
+            Light::Point(light) => {
+                xml.push_str("<lichtquelle>\n");
+                vector3(&mut xml, "position", light.pos);
+                rgb(&mut xml, "farbe", light.color);
+                xml.push_str("</lichtquelle>\n");
+            }
+            Light::Directional(light) => {
+                xml.push_str("<richtungslicht>\n");
+                vector3(&mut xml, "richtung", light.dir);
+                rgb(&mut xml, "farbe", light.color);
+                xml.push_str("</richtungslicht>\n");
+            }
+            Light::Spot(light) => {
+                writeln!(
+                    xml,
+                    r#"<spotlicht winkel="{}" abfall="{}">"#,
+                    (light.angle as f64).to_degrees(),
+                    light.falloff
+                )
+                .unwrap();
+                vector3(&mut xml, "position", light.pos);
+                vector3(&mut xml, "richtung", light.dir);
+                rgb(&mut xml, "farbe", light.color);
+                xml.push_str("</spotlicht>\n");
+            }
+            Light::Area(light) => write_area_light(&mut xml, scene, light),
+        }
+    }
+    xml.push_str("</beleuchtung>\n");
+    xml.push_str("</szene>\n");
+    xml
+}
+
+// Speichert die Szene unter path und ihre Dreiecke daneben als <Name>_triangulation.xml
+pub fn save_scene(scene: &Scene, path: &Path) -> Result<(), anyhow::Error> {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("szene");
+    let src = format!("{}_triangulation.xml", stem);
+    let triangulation_path = path.with_file_name(&src);
+    save_triangulation(&triangulation_path, &scene.materials, &scene.mesh)?;
+    fs::write(path, write_scene(scene, &src))
+        .with_context(|| format!("Cannot write scene file {}", path.display()))
+}
+
+// Speichert Materialien und Dreiecke im an der Dateiendung erkannten Format, .xml, .obj (mit .mtl daneben) oder .ply
+pub fn save_triangulation(
+    path: &Path,
+    materials: &[Material],
+    mesh: &Mesh,
+) -> Result<(), anyhow::Error> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "xml" => fs::write(path, write_triangulation(materials, mesh))
+            .with_context(|| format!("Cannot write triangulation {}", path.display())),
+        "obj" => obj::save_obj(path, materials, mesh),
+        "ply" => ply::save_ply(path, materials, mesh),
+        _ => Err(anyhow!("Unsupported export format: {}", extension)),
+    }
+}
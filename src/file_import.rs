@@ -1,40 +1,87 @@
 use crate::camera::Camera;
 use crate::color::Color;
-use crate::light_source::LightSource;
-use crate::material::Material;
+use crate::dtd::{Dtd, ValidationError, SZENE, TRIANGULATION};
+use crate::gltf_import;
+use crate::light_source::{
+    AreaLight, Attenuation, DirectionalLight, Light, LightSource, Shape, SpotLight, AREA_LIGHT_SAMPLES,
+};
+use crate::material::{Material, BRECHUNGSINDEX_GLAS};
+use crate::obj;
+use crate::ply;
 use crate::scene::Scene;
+use crate::scene_error::SceneError;
+use crate::stl;
 use crate::triangle::Triangle;
 use glam::f32::Vec3A;
-use roxmltree::{Document, Node};
-use std::collections::HashMap;
+use roxmltree::{Document, Node, TextPos};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::vec::Vec;
 
-use anyhow::anyhow;
-
+// Errors are created without a path, load_scene and load_triangulation add it with SceneError::in_file
 macro_rules! childNode {
     ($e:expr,$t:expr) => {
         $e.children()
             .find(|e| e.has_tag_name($t))
-            .ok_or(anyhow!("Element has no child element {}", $t))
+            .ok_or_else(|| SceneError::MissingElement {
+                path: PathBuf::new(),
+                pos: pos($e, $e.range().start),
+                parent: $e.tag_name().name().to_string(),
+                name: $t.to_string(),
+            })
     };
 }
 
-fn fatt(ele: &Node, att: &str) -> Result<f32, anyhow::Error> {
-    Ok(ele
-        .attribute(att)
-        .ok_or(anyhow!("Missing float attribute: {}", att))?
-        .parse::<f32>()?)
+fn pos(e: &Node, offset: usize) -> TextPos {
+    e.document().text_pos_at(offset)
 }
 
-fn satt(ele: &Node, att: &str) -> Result<String, anyhow::Error> {
+fn attribute_start(ele: &Node, att: &str) -> usize {
+    ele.attribute_node(att)
+        .map_or(ele.range().start, |a| a.range().start)
+}
+
+fn satt(ele: &Node, att: &str) -> Result<String, SceneError> {
     Ok(ele
         .attribute(att)
-        .ok_or(anyhow!("Missing string attribute: {}", att))?
+        .ok_or_else(|| SceneError::MissingAttribute {
+            path: PathBuf::new(),
+            pos: pos(ele, ele.range().start),
+            element: ele.tag_name().name().to_string(),
+            name: att.to_string(),
+        })?
         .to_string())
 }
 
-fn parse_vector3(e: &Node) -> Result<Vec3A, anyhow::Error> {
+fn number<T: FromStr>(ele: &Node, att: &str) -> Result<T, SceneError> {
+    let value = satt(ele, att)?;
+    value.trim().parse().map_err(|_| SceneError::BadNumber {
+        path: PathBuf::new(),
+        pos: pos(ele, attribute_start(ele, att)),
+        attribute: att.to_string(),
+        value,
+    })
+}
+
+fn fatt(ele: &Node, att: &str) -> Result<f32, SceneError> {
+    number(ele, att)
+}
+
+fn uatt(ele: &Node, att: &str) -> Result<u32, SceneError> {
+    number(ele, att)
+}
+
+// optional float attribute
+fn oatt(ele: &Node, att: &str, default: f32) -> Result<f32, SceneError> {
+    match ele.attribute(att) {
+        Some(_) => fatt(ele, att),
+        None => Ok(default),
+    }
+}
+
+fn parse_vector3(e: &Node) -> Result<Vec3A, SceneError> {
     Ok(Vec3A::new(fatt(e, "x")?, fatt(e, "y")?, fatt(e, "z")?))
 }
 #[test]
@@ -44,12 +91,12 @@ fn test_get_vector3d_from_element() {
     assert_eq!(v, Vec3A::new(-0.7, 1.4, 0.38));
 }
 
-fn parse_color(e: &Node) -> Result<Color, anyhow::Error> {
+fn parse_color(e: &Node) -> Result<Color, SceneError> {
     Ok(Color::new(
         fatt(e, "r")?,
         fatt(e, "g")?,
         fatt(e, "b")?,
-        fatt(e, "a").unwrap_or(1.0),
+        oatt(e, "a", 1.0)?,
     ))
 }
 /*
@@ -62,7 +109,8 @@ fn itArray(it: &mut dyn Iterator<Item = Node>) -> Result<[Vec3A; 3],anyhow::Erro
     ])
 }
 */
-fn parse_triangle(e: &Node) -> Result<Triangle, anyhow::Error> {
+// material_ids maps material names to indices into Scene::materials
+fn parse_triangle(e: &Node, material_ids: &HashMap<String, usize>) -> Result<Triangle, SceneError> {
     let p = [
         parse_vector3(&childNode!(&e, "punkt1")?)?,
         parse_vector3(&childNode!(&e, "punkt2")?)?,
@@ -73,7 +121,16 @@ fn parse_triangle(e: &Node) -> Result<Triangle, anyhow::Error> {
         parse_vector3(&childNode!(&e, "normale2")?)?,
         parse_vector3(&childNode!(&e, "normale3")?)?,
     ];
-    Ok(Triangle::with_normals(p, n))
+    let name = satt(e, "material")?;
+    let mut t = Triangle::with_normals(p, n);
+    t.material = *material_ids
+        .get(&name)
+        .ok_or_else(|| SceneError::UnknownMaterial {
+            path: PathBuf::new(),
+            pos: pos(e, attribute_start(e, "material")),
+            name: name.clone(),
+        })?;
+    Ok(t)
 }
 #[test]
 fn test_parse_triangle() {
@@ -86,38 +143,70 @@ fn test_parse_triangle() {
 <normale3 x="-0.4" y="4.4" z="0.4"/>
 </dreieck>"#;
     let doc = Document::parse(XML).unwrap();
-    let t: Triangle = parse_triangle(&doc.root_element()).unwrap();
+    let material_ids = HashMap::from([("blau".to_string(), 0), ("gelb".to_string(), 1)]);
+    let t: Triangle = parse_triangle(&doc.root_element(), &material_ids).unwrap();
     assert_eq!(t.p[2], Vec3A::new(-0.7, 1.4, 0.38));
     assert_eq!(t.n[0], Vec3A::new(-0.4, 4.4, 0.4));
+    assert_eq!(t.material, 1);
+    let err = parse_triangle(&doc.root_element(), &HashMap::new()).unwrap_err();
+    assert_eq!(err.to_string(), "line 1, column 10: unknown material gelb");
+    let comma = XML.replace("-0.7", "-0,7");
+    let doc = Document::parse(&comma).unwrap();
+    let err = parse_triangle(&doc.root_element(), &material_ids).unwrap_err();
+    assert!(matches!(
+        err,
+        SceneError::BadNumber { pos, ref value, .. } if pos == TextPos::new(4, 9) && value == "-0,7"
+    ));
 }
 
-fn parse_triangulation(
-    xml: &str,
-) -> Result<(HashMap<String, Material>, Vec<Triangle>), anyhow::Error> {
-    let doc = Document::parse(xml)?;
+// Parst das Dokument und prüft es gegen die DTD, alle Verstöße werden gemeinsam gemeldet
+fn parse_document<'a>(xml: &'a str, dtd: &Dtd, root: &str) -> Result<Document<'a>, SceneError> {
+    let doc = Document::parse(xml).map_err(|e| SceneError::Xml {
+        path: PathBuf::new(),
+        error: ValidationError::from_xml(&e),
+    })?;
+    dtd.validate(&doc, root)
+        .map_err(|errors| SceneError::Invalid {
+            path: PathBuf::new(),
+            errors: errors.0,
+        })?;
+    Ok(doc)
+}
+
+// Triangle::material is an index into the returned materials
+#[allow(non_snake_case)]
+pub fn parse_triangulation(xml: &str) -> Result<(Vec<Material>, Vec<Triangle>), SceneError> {
+    let doc = parse_document(xml, &TRIANGULATION, "triangulation")?;
     let e = doc.root_element();
     let matEles = e.children().filter(|e| e.has_tag_name("material"));
-    let mut materials: HashMap<String, Material> = HashMap::new();
+    let mut materials: Vec<Material> = Vec::new();
+    let mut material_ids: HashMap<String, usize> = HashMap::new();
     for m in matEles {
         let ambient = parse_color(&childNode!(&m, "ambient")?)?;
         let diffus = parse_color(&childNode!(&m, "diffus")?)?;
         let spiegelnd = parse_color(&childNode!(&m, "spiegelnd")?)?;
         let name = satt(&m, "name")?;
-        materials.insert(
-            name.to_string(),
-            Material {
-                name: name.to_string(),
-                glanz: fatt(&m, "glanzwert")?,
-                ambient,
-                diffus,
-                spiegelnd,
-            },
-        );
+        if material_ids.insert(name.to_string(), materials.len()).is_some() {
+            return Err(SceneError::DuplicateMaterial {
+                path: PathBuf::new(),
+                pos: pos(&m, m.range().start),
+                name,
+            });
+        }
+        materials.push(Material {
+            name: name.to_string(),
+            glanz: fatt(&m, "glanzwert")?,
+            // not part of the PfannkuchenEdit format, so optional
+            brechungsindex: oatt(&m, "brechungsindex", BRECHUNGSINDEX_GLAS)?,
+            ambient,
+            diffus,
+            spiegelnd,
+        });
     }
     let triangleEles = e.children().filter(|e| e.has_tag_name("dreieck"));
     let mut triangles: Vec<Triangle> = Vec::new();
     for te in triangleEles {
-        triangles.push(parse_triangle(&te)?);
+        triangles.push(parse_triangle(&te, &material_ids)?);
     }
     Ok((materials, triangles))
 }
@@ -138,8 +227,26 @@ z="0.78"/>
 <normale3 x="-0.42" y="4.40" z="0.43"/>
 </dreieck><dreieck material="gelb"><punkt1 x="-1.66" y="1.18" z="0.78"/><punkt2 x="-0.71" y="1.40" z="0.55"/><punkt3 x="-0.73" y="1.40" z="0.38"/><normale1 x="-0.39" y="4.40" z="0.52"/><normale2 x="-0.72" y="4.29" z="0.56"/><normale3 x="-0.42" y="4.40" z="0.43"/></dreieck></triangulation>"#;
     let (materials, triangles) = parse_triangulation(XML).unwrap();
-    assert_eq!(materials.get("blau").unwrap().ambient.a, 1.0);
+    assert_eq!(materials[0].name, "blau");
+    assert_eq!(materials[0].ambient.a, 1.0);
     assert_eq!(triangles[0].p[0], Vec3A::new(-1.71, 1.18, 0.38));
+    assert_eq!(materials[triangles[1].material].name, "gelb");
+    let unknown = XML.replace(r#"<dreieck material="gelb"><punkt1 x="-1.66""#, r#"<dreieck material="lila"><punkt1 x="-1.66""#);
+    let err = parse_triangulation(&unknown).unwrap_err();
+    assert_eq!(err.to_string(), "line 14, column 20: unknown material lila");
+    let invalid = XML.replace("<normale3 x=\"-0.42\" y=\"4.40\" z=\"0.43\"/>\n", "");
+    match parse_triangulation(&invalid).unwrap_err() {
+        SceneError::Invalid { errors, .. } => assert_eq!(
+            errors[0].to_string(),
+            "line 13, column 1: <dreieck> ends before <normale3>"
+        ),
+        err => panic!("{}", err),
+    }
+    let duplicate = XML.replace(r#"name="gelb""#, r#"name="blau""#);
+    assert!(matches!(
+        parse_triangulation(&duplicate),
+        Err(SceneError::DuplicateMaterial { .. })
+    ));
 }
 
 /*
@@ -148,34 +255,235 @@ fn childNode<'a>(e: &'a Node, tagName: &'static str) -> Result<&'a Node<'a, 'a>,
     Ok(&e.children().find(|e| e.has_tag_name("position")).ok_or(anyhow!("Element has no child element {}",tagName))?)
 }
 */
-fn parse_camera(e: &Node) -> Result<Camera, anyhow::Error> {
+fn parse_camera(e: &Node) -> Result<Camera, SceneError> {
     let pos = parse_vector3(&childNode!(&e, "position")?)?;
     let target = parse_vector3(&childNode!(&e, "ziel")?)?;
-    Ok(Camera::new(pos, target))
+    let mut camera = Camera::new(pos, target);
+    // the scene file specifies the angle in degrees, converted in double precision like in file_export
+    let winkel: f64 = number(&childNode!(&e, "fovy")?, "winkel")?;
+    camera.fovy = winkel.to_radians() as f32;
+    Ok(camera)
 }
 
-fn parse_lightsource(e: &Node) -> Result<LightSource, anyhow::Error> {
+fn parse_attenuation(e: &Node) -> Result<Attenuation, SceneError> {
+    Ok(Attenuation {
+        konstant: fatt(e, "konstant")?,
+        linear: fatt(e, "linear")?,
+        quadratisch: fatt(e, "quadratisch")?,
+    })
+}
+
+fn parse_lightsource(e: &Node) -> Result<LightSource, SceneError> {
     let pos = parse_vector3(&childNode!(&e, "position")?)?;
     let color = parse_color(&childNode!(&e, "farbe")?)?;
     Ok(LightSource { pos, color })
 }
 
-pub fn parse_scene(xml: &str) -> Result<Scene, anyhow::Error> {
-    let doc = Document::parse(xml).expect("Parsing XML");
+// winkel ist der halbe Öffnungswinkel in Grad
+fn parse_spot_light(e: &Node) -> Result<SpotLight, SceneError> {
+    let winkel: f64 = number(e, "winkel")?;
+    Ok(SpotLight {
+        pos: parse_vector3(&childNode!(&e, "position")?)?,
+        dir: parse_vector3(&childNode!(&e, "richtung")?)?,
+        color: parse_color(&childNode!(&e, "farbe")?)?,
+        angle: winkel.to_radians() as f32,
+        falloff: oatt(e, "abfall", 0.0)?,
+    })
+}
+
+// Flächenlichtquelle, <material> macht alle Dreiecke dieses Materials zu einer leuchtenden Fläche
+fn parse_area_light(
+    e: &Node,
+    materials: &[Material],
+    triangles: &[Triangle],
+) -> Result<AreaLight, SceneError> {
+    let color = parse_color(&childNode!(&e, "farbe")?)?;
+    let samples = match e.attribute("abtastungen") {
+        Some(_) => uatt(e, "abtastungen")?,
+        None => AREA_LIGHT_SAMPLES,
+    };
+    // the DTD ensures that the first child element is the shape
+    let s = childNode!(&e, "rechteck")
+        .or_else(|_| childNode!(&e, "scheibe"))
+        .or_else(|_| childNode!(&e, "kugel"))
+        .or_else(|_| childNode!(&e, "dreieck"))
+        .or_else(|_| childNode!(&e, "material"))?;
+    let vector = |name: &str| parse_vector3(&childNode!(&s, name)?);
+    let shape = match s.tag_name().name() {
+        "rechteck" => Shape::Rectangle {
+            center: vector("position")?,
+            edge1: vector("kante1")?,
+            edge2: vector("kante2")?,
+        },
+        "scheibe" => Shape::Disk {
+            center: vector("position")?,
+            normal: vector("normale")?.normalize(),
+            radius: fatt(&s, "radius")?,
+        },
+        "kugel" => Shape::Sphere {
+            center: vector("position")?,
+            radius: fatt(&s, "radius")?,
+        },
+        "dreieck" => Shape::Triangle([vector("punkt1")?, vector("punkt2")?, vector("punkt3")?]),
+        _ => {
+            let name = satt(&s, "name")?;
+            let material = materials
+                .iter()
+                .position(|m| m.name == name)
+                .ok_or_else(|| SceneError::UnknownMaterial {
+                    path: PathBuf::new(),
+                    pos: pos(&s, attribute_start(&s, "name")),
+                    name,
+                })?;
+            Shape::emissive(
+                material,
+                triangles
+                    .iter()
+                    .filter(|t| t.material == material)
+                    .map(|t| t.p)
+                    .collect(),
+            )
+        }
+    };
+    Ok(AreaLight {
+        shape,
+        color,
+        samples,
+    })
+}
+
+// Lädt Materialien und Dreiecke, das Format richtet sich nach der Dateiendung: xml (PfannkuchenEdit), obj, ply, stl, gltf oder glb
+pub fn load_triangulation(path: &Path) -> Result<(Vec<Material>, Vec<Triangle>), SceneError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let io = |source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let import = match extension.as_str() {
+        "xml" => {
+            let xml = fs::read_to_string(path).map_err(io)?;
+            return parse_triangulation(&xml).map_err(|e| e.in_file(path));
+        }
+        "obj" => obj::load_obj,
+        "ply" => ply::load_ply,
+        "stl" => stl::load_stl,
+        "gltf" | "glb" => gltf_import::load_gltf,
+        _ => {
+            return Err(SceneError::UnsupportedFormat {
+                path: path.to_path_buf(),
+                extension,
+            })
+        }
+    };
+    // a missing or unreadable file is reported as Io, like for XML, and not hidden in the importer's message
+    fs::metadata(path).map_err(io)?;
+    let (mut materials, triangles) = import(path).map_err(|source| SceneError::Import {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    unique_names(&mut materials);
+    Ok((materials, triangles))
+}
+
+// Szenen und Triangulierungen verweisen über den Namen auf Materialien, doppelte Namen, etwa aus glTF-Dateien,
+// bekommen deshalb die Endung _1, _2 usw.
+fn unique_names(materials: &mut [Material]) {
+    let names: HashSet<String> = materials.iter().map(|m| m.name.clone()).collect();
+    let mut seen = HashSet::new();
+    for m in materials {
+        if seen.insert(m.name.clone()) {
+            continue;
+        }
+        let name = (1..)
+            .map(|i| format!("{}_{}", m.name, i))
+            .find(|n| !names.contains(n) && !seen.contains(n))
+            .unwrap();
+        seen.insert(name.clone());
+        m.name = name;
+    }
+}
+
+// Lädt eine Szenendatei, relative Pfade darin beziehen sich auf das Verzeichnis der Szenendatei.
+// triangulation ersetzt die in <triangulation src> angegebene Datei. Wie bei Scene::new ist noch keine
+// Beschleunigungsstruktur aufgebaut, der Aufrufer wählt sie mit Scene::build_accelerator.
+pub fn load_scene(path: &Path, triangulation: Option<&Path>) -> Result<Scene, SceneError> {
+    let xml = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&xml, base, triangulation).map_err(|e| e.in_file(path))
+}
+#[test]
+fn test_load_scene() {
+    let scene = load_scene(Path::new("scene/abgabeszene.xml"), None).unwrap();
+    assert_eq!(scene.mesh.len(), 3456);
+    let err = load_scene(Path::new("scene/missing.xml"), None).unwrap_err();
+    assert!(matches!(err, SceneError::Io { .. }));
+    assert!(err.to_string().starts_with("scene/missing.xml: "));
+    let missing = Path::new("scene/missing.stl");
+    let err = load_scene(Path::new("scene/abgabeszene.xml"), Some(missing)).unwrap_err();
+    assert!(matches!(err, SceneError::Io { .. }));
+    assert_eq!(err.path(), missing);
+    let err = load_triangulation(Path::new("scene/szene.dtd")).unwrap_err();
+    assert!(
+        matches!(err, SceneError::UnsupportedFormat { ref extension, .. } if extension == "dtd")
+    );
+    let cube = Path::new("scene/wuerfel.obj");
+    let scene = load_scene(Path::new("scene/abgabeszene.xml"), Some(cube)).unwrap();
+    assert_eq!(scene.mesh.len(), 12);
+    assert_eq!(scene.materials[1].name, "metall");
+}
+
+// base is the directory that relative <triangulation src> paths are resolved against, unless triangulation is given
+pub fn parse_scene(
+    xml: &str,
+    base: &Path,
+    triangulation: Option<&Path>,
+) -> Result<Scene, SceneError> {
+    let doc = parse_document(xml, &SZENE, "szene")?;
     let e = doc.root_element();
-    let triangulation_src = "scene/".to_owned() + &satt(&childNode!(&e, "triangulation")?, "src")?;
-    let txml = fs::read_to_string(triangulation_src)?;
-    let (materials, triangles) = parse_triangulation(&txml).unwrap();
+    let triangulation_src = match triangulation {
+        Some(path) => path.to_path_buf(),
+        // joining an absolute path replaces base
+        None => base.join(satt(&childNode!(&e, "triangulation")?, "src")?),
+    };
+    let (materials, triangles) = load_triangulation(&triangulation_src)?;
+    let fenster = &childNode!(&e, "fenster")?;
+    let width = uatt(fenster, "breite")?;
+    let height = uatt(fenster, "hoehe")?;
+    let subdivision = uatt(&childNode!(&e, "raumteilung")?, "unterteilung")?;
     let camera = parse_camera(&childNode!(&e, "kamera")?)?;
     let beleuchtung = &childNode!(&e, "beleuchtung")?;
     let background = parse_color(&childNode!(&beleuchtung, "hintergrundfarbe")?)?;
     let ambient = parse_color(&childNode!(&beleuchtung, "ambientehelligkeit")?)?;
+    let attenuation = parse_attenuation(&childNode!(&beleuchtung, "abschwaechung")?)?;
+    let mut lights = Vec::new();
+    for l in beleuchtung.children() {
+        lights.push(match l.tag_name().name() {
+            "lichtquelle" => Light::Point(parse_lightsource(&l)?),
+            "richtungslicht" => Light::Directional(DirectionalLight {
+                dir: parse_vector3(&childNode!(&l, "richtung")?)?,
+                color: parse_color(&childNode!(&l, "farbe")?)?,
+            }),
+            "spotlicht" => Light::Spot(parse_spot_light(&l)?),
+            "flaechenlicht" => Light::Area(parse_area_light(&l, &materials, &triangles)?),
+            _ => continue,
+        });
+    }
     Ok(Scene {
-        camera,
-        materials,
-        triangles,
+        width,
+        height,
+        subdivision,
         background,
         ambient,
+        attenuation,
+        lights,
+        ..Scene::new(camera, materials, triangles)
     })
 }
 /*<?xml version="1.0" encoding="UTF-8"?>
@@ -209,5 +517,110 @@ fn test_parse_scene() {
   </beleuchtung>
 </szene>
 "#;
-    let _scene = parse_scene(XML).unwrap();
+    let scene = parse_scene(XML, Path::new("scene"), None).unwrap();
+    assert_eq!((scene.width, scene.height), (320, 240));
+    assert_eq!(scene.subdivision, 2);
+    assert_eq!(scene.camera.fovy, 45f32.to_radians());
+    assert_eq!(scene.camera.pos, Vec3A::new(-2.46, 7.86, 9.51));
+    assert_eq!(scene.background, Color::new(0.2, 0.2, 0.5, 1.0));
+    assert_eq!(scene.attenuation.konstant, 1.0);
+    assert_eq!(scene.attenuation.linear, 0.0);
+    assert_eq!(scene.attenuation.quadratisch, 1.0);
+    assert_eq!(scene.lights.len(), 2);
+    assert_eq!(
+        scene.lights[1],
+        Light::Point(LightSource {
+            pos: Vec3A::new(-3.00, 8.35, 10.65),
+            color: Color::white()
+        })
+    );
+    let absolute = fs::canonicalize("scene").unwrap();
+    assert!(parse_scene(XML, &absolute, None).is_ok());
+    let err = parse_scene(XML, Path::new("elsewhere"), None).unwrap_err();
+    assert!(err.to_string().contains("abgabetriangulation_high.xml"));
+    // syntax errors and every violation of szene.dtd are reported with their position instead of panicking
+    let err = parse_scene("<szene>\n</kamera>", Path::new("scene"), None).unwrap_err();
+    assert!(matches!(err, SceneError::Xml { ref error, .. } if error.line == 2));
+    let invalid = XML
+        .replace(r#" hoehe="240""#, "")
+        .replace(r#"<fovy winkel="45.0"/>"#, "");
+    let err = parse_scene(&invalid, Path::new("scene"), None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "violates the DTD
+  line 4, column 3: <fenster> is missing the required attribute hoehe
+  line 10, column 3: <kamera> ends before <fovy>"
+    );
+    let bad = XML.replace(r#"breite="320""#, r#"breite="-320""#);
+    let err = parse_scene(&bad, Path::new("scene"), None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 4, column 12: invalid number -320 in attribute breite"
+    );
+    // area, directional and spot lights may follow or replace the point lights
+    let area = XML.replace(
+        "  </beleuchtung>",
+        r#"    <flaechenlicht>
+      <material name="gelb"/>
+      <farbe b="0.5" g="1.0" r="1.0"/>
+    </flaechenlicht>
+    <flaechenlicht abtastungen="4">
+      <kugel radius="0.5"><position x="1" y="2" z="3"/></kugel>
+      <farbe b="1.0" g="1.0" r="1.0"/>
+    </flaechenlicht>
+    <richtungslicht>
+      <richtung x="0" y="-1" z="0"/>
+      <farbe b="0.8" g="0.9" r="1.0"/>
+    </richtungslicht>
+    <spotlicht winkel="30" abfall="2">
+      <position x="0" y="5" z="0"/>
+      <richtung x="0" y="-1" z="0"/>
+      <farbe b="1.0" g="1.0" r="1.0"/>
+    </spotlicht>
+  </beleuchtung>"#,
+    );
+    let scene = parse_scene(&area, Path::new("scene"), None).unwrap();
+    assert_eq!(scene.lights.len(), 6);
+    match &scene.lights[2] {
+        Light::Area(AreaLight {
+            shape: Shape::Emissive {
+                material,
+                triangles,
+                cdf,
+            },
+            samples,
+            ..
+        }) => {
+            assert_eq!(scene.materials[*material].name, "gelb");
+            assert!(!triangles.is_empty());
+            assert_eq!(cdf.len(), triangles.len());
+            assert_eq!(*samples, AREA_LIGHT_SAMPLES);
+        }
+        light => panic!("{:?}", light),
+    }
+    assert_eq!(scene.emission(1), Color::new(1.0, 1.0, 0.5, 1.0));
+    assert!(matches!(
+        &scene.lights[3],
+        Light::Area(AreaLight { shape: Shape::Sphere { radius, .. }, samples: 4, .. }) if *radius == 0.5
+    ));
+    assert_eq!(
+        scene.lights[4],
+        Light::Directional(DirectionalLight {
+            dir: -Vec3A::Y,
+            color: Color::new(1.0, 0.9, 0.8, 1.0)
+        })
+    );
+    assert!(matches!(
+        &scene.lights[5],
+        Light::Spot(SpotLight { angle, falloff, .. })
+            if *angle == 30f32.to_radians() && *falloff == 2.0
+    ));
+    let unknown = area.replace(r#"name="gelb""#, r#"name="lila""#);
+    let err = parse_scene(&unknown, Path::new("scene"), None).unwrap_err();
+    assert!(matches!(err, SceneError::UnknownMaterial { ref name, .. } if name == "lila"));
+    let shapeless = area.replace(r#"<material name="gelb"/>"#, "");
+    assert!(matches!(
+        parse_scene(&shapeless, Path::new("scene"), None),
+        Err(SceneError::Invalid { .. })
+    ));
 }
@@ -0,0 +1,367 @@
+// Import von glTF 2.0 (.gltf mit eingebetteten oder separaten Puffern sowie .glb)
+
+use crate::color::Color;
+use crate::material::{Material, BRECHUNGSINDEX_GLAS};
+use crate::mesh_processing::generate_missing_normals;
+use crate::triangle::Triangle;
+use anyhow::{anyhow, Context};
+use base64::Engine;
+use glam::f32::{Mat4, Vec3, Vec3A};
+use gltf::buffer::Source;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use std::fs;
+use std::path::Path;
+
+#[cfg(test)]
+// one triangle with u16 indices, positions at byte 0, indices at byte 36
+fn test_buffer() -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for x in [0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+        buffer.extend(x.to_le_bytes());
+    }
+    for i in [0u16, 1, 2] {
+        buffer.extend(i.to_le_bytes());
+    }
+    buffer
+}
+
+#[cfg(test)]
+fn test_json(uri: Option<&str>) -> String {
+    let uri = uri.map_or(String::new(), |u| format!(r#", "uri": "{}""#, u));
+    format!(
+        r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0]}}],
+  "nodes": [
+    {{"children": [1], "translation": [0, 0, 1]}},
+    {{"mesh": 0, "scale": [2, 2, 2]}}
+  ],
+  "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "indices": 1, "material": 0}}]}}],
+  "materials": [{{
+    "name": "gold",
+    "pbrMetallicRoughness": {{"baseColorFactor": [1, 0.8, 0, 1], "metallicFactor": 1, "roughnessFactor": 0.5}}
+  }}],
+  "buffers": [{{"byteLength": 42{}}}],
+  "bufferViews": [
+    {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+  ],
+  "accessors": [
+    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]}},
+    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+  ]
+}}"#,
+        uri
+    )
+}
+
+#[cfg(test)]
+fn assert_test_triangle(materials: &[Material], triangles: &[Triangle]) {
+    assert_eq!(triangles.len(), 1);
+    let t = &triangles[0];
+    // scaled by the child node, then translated by its parent
+    assert_eq!(
+        t.p,
+        [
+            Vec3A::new(0.0, 0.0, 1.0),
+            Vec3A::new(2.0, 0.0, 1.0),
+            Vec3A::new(0.0, 2.0, 1.0)
+        ]
+    );
+    assert_eq!(t.n, [Vec3A::Z; 3]);
+    assert_eq!(materials[t.material].name, "gold");
+}
+
+#[test]
+fn test_parse_gltf() {
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(test_buffer())
+    );
+    let json = test_json(Some(&uri));
+    let (materials, triangles) = parse_gltf(json.as_bytes(), &mut |_| unreachable!()).unwrap();
+    assert_test_triangle(&materials, &triangles);
+    let gold = &materials[0];
+    // a metal has no diffuse part, its specular color is the base color
+    assert_eq!(gold.diffus, Color::new(0.0, 0.0, 0.0, 1.0));
+    assert_eq!(gold.spiegelnd.r, 1.0);
+    assert_eq!(gold.spiegelnd.a, 0.5);
+    assert!(gold.glanz > 1.0);
+
+    // relative URIs are percent-encoded
+    let json = test_json(Some("ein%20dreieck%C3%A4.bin"));
+    let (materials, triangles) = parse_gltf(json.as_bytes(), &mut |uri| {
+        assert_eq!(uri, "ein dreieckä.bin");
+        Ok(test_buffer())
+    })
+    .unwrap();
+    assert_test_triangle(&materials, &triangles);
+    assert!(parse_gltf(json.as_bytes(), &mut |_| Ok(vec![0; 10])).is_err());
+    assert!(percent_decode("dreieck%2.bin").is_err());
+    assert!(percent_decode("%+1.bin").is_err());
+    assert!(percent_decode("%FF.bin").is_err());
+}
+
+#[test]
+fn test_duplicate_names() {
+    use crate::file_export::save_triangulation;
+    use crate::file_import::load_triangulation;
+    // Blender names every new material "Material", and a material may be called like the default material
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(test_buffer())
+    );
+    let json = test_json(Some(&uri)).replace(
+        r#""materials": [{"#,
+        r#""materials": [{"name": "gold"}, {"name": "default"}, {"#,
+    );
+    let dir = std::env::temp_dir().join(format!("raytracer_gltf_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("doppelt.gltf");
+    fs::write(&path, json).unwrap();
+    let (materials, triangles) = load_triangulation(&path).unwrap();
+    let names: Vec<&str> = materials.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["gold", "default", "gold_1", "default_1"]);
+    let copy = dir.join("kopie.xml");
+    let mesh = crate::mesh::Mesh::from_triangles(&triangles);
+    save_triangulation(&copy, &materials, &mesh).unwrap();
+    let (parsed_materials, triangles) = load_triangulation(&copy).unwrap();
+    assert_eq!(parsed_materials, materials);
+    assert_eq!(crate::mesh::Mesh::from_triangles(&triangles), mesh);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_glb() {
+    let mut json = test_json(None).into_bytes();
+    let mut bin = test_buffer();
+    // chunks are padded to 4 bytes, JSON with spaces
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let mut glb = b"glTF".to_vec();
+    glb.extend(2u32.to_le_bytes());
+    glb.extend((12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(b"JSON");
+    glb.extend(json);
+    glb.extend((bin.len() as u32).to_le_bytes());
+    glb.extend(b"BIN\0");
+    glb.extend(bin);
+    let (materials, triangles) = parse_gltf(&glb, &mut |_| unreachable!()).unwrap();
+    assert_test_triangle(&materials, &triangles);
+}
+
+// Näherung des PBR-Materials durch das Phong-Modell: Metalle reflektieren in ihrer Grundfarbe, Nichtmetalle streuen
+// diffus und spiegeln 4 %. Glatte Oberflächen werden zu Spiegeln, die Rauheit bestimmt den Glanzwert.
+fn convert_material(m: &gltf::Material) -> Material {
+    let pbr = m.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    let metallic = pbr.metallic_factor();
+    let roughness = pbr.roughness_factor();
+    let base = Color::new(r, g, b, 1.0);
+    let dielectric = Color::new(0.04, 0.04, 0.04, 1.0);
+    let mut spiegelnd = dielectric * (1.0 - metallic) + base * metallic;
+    spiegelnd.a = 1.0 - metallic * (1.0 - roughness);
+    let mut diffus = base * (1.0 - metallic);
+    // only blended materials let light through, masked ones are treated as opaque
+    diffus.a = if m.alpha_mode() == AlphaMode::Blend {
+        a
+    } else {
+        1.0
+    };
+    // Blinn-Phong exponent for the GGX width roughness²
+    let alpha = roughness * roughness;
+    let glanz = (2.0 / (alpha * alpha).max(1e-4) - 2.0).clamp(1.0, 1000.0);
+    Material {
+        name: m.name().map_or_else(
+            || format!("material{}", m.index().unwrap_or(0)),
+            String::from,
+        ),
+        ambient: base,
+        diffus,
+        spiegelnd,
+        glanz,
+        brechungsindex: BRECHUNGSINDEX_GLAS,
+    }
+}
+
+// URIs may contain %XX escapes of UTF-8 bytes, e.g. %20 for a space
+fn percent_decode(uri: &str) -> Result<String, anyhow::Error> {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or(anyhow!("Invalid escape in glTF URI {}", uri))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| anyhow!("glTF URI {} is not UTF-8", uri))
+}
+
+// Inhalt eines Puffers: BIN-Abschnitt einer .glb-Datei, data-URI mit Base64 oder separate Datei
+fn load_buffers(
+    gltf: &gltf::Gltf,
+    load_buffer: &mut dyn FnMut(&str) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+    let mut buffers = Vec::new();
+    for buffer in gltf.buffers() {
+        let data = match buffer.source() {
+            Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or(anyhow!("glTF buffer {} without BIN chunk", buffer.index()))?,
+            Source::Uri(uri) if uri.starts_with("data:") => {
+                let (_, encoded) = uri.split_once(";base64,").ok_or(anyhow!(
+                    "Unsupported data URI in glTF buffer {}",
+                    buffer.index()
+                ))?;
+                base64::engine::general_purpose::STANDARD.decode(encoded)?
+            }
+            Source::Uri(uri) => load_buffer(&percent_decode(uri)?)?,
+        };
+        if data.len() < buffer.length() {
+            return Err(anyhow!(
+                "glTF buffer {} has {} bytes instead of {}",
+                buffer.index(),
+                data.len(),
+                buffer.length()
+            ));
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+// Hängt die Dreiecke des Knotens und seiner Kinder an, transform ist die Transformation des Elternknotens
+fn add_node(
+    node: &gltf::Node,
+    transform: Mat4,
+    buffers: &[Vec<u8>],
+    default_material: usize,
+    triangles: &mut Vec<Triangle>,
+    missing_normals: &mut Vec<usize>,
+) -> Result<(), anyhow::Error> {
+    let transform = transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+    // normals are transformed with the inverse transpose, mirroring transforms reverse the winding order
+    let normal_matrix = transform.inverse().transpose();
+    let mirrored = transform.determinant() < 0.0;
+    for primitive in node.mesh().iter().flat_map(|m| m.primitives()) {
+        let reader = primitive.reader(|b| buffers.get(b.index()).map(Vec::as_slice));
+        let positions: Vec<Vec3A> = reader
+            .read_positions()
+            .ok_or(anyhow!("glTF primitive without positions"))?
+            .map(|p| Vec3A::from(transform.transform_point3(Vec3::from(p))))
+            .collect();
+        let normals: Option<Vec<Vec3A>> = reader.read_normals().map(|normals| {
+            normals
+                .map(|n| Vec3A::from(normal_matrix.transform_vector3(Vec3::from(n))))
+                .collect()
+        });
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if let Some(&i) = indices.iter().find(|&&i| i >= positions.len()) {
+            return Err(anyhow!("glTF vertex index out of range: {}", i));
+        }
+        let faces: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices
+                .chunks_exact(3)
+                .map(|f| [f[0], f[1], f[2]])
+                .collect(),
+            Mode::TriangleStrip => (2..indices.len())
+                .map(|k| match k % 2 {
+                    0 => [indices[k - 2], indices[k - 1], indices[k]],
+                    _ => [indices[k - 1], indices[k - 2], indices[k]],
+                })
+                .collect(),
+            Mode::TriangleFan => (2..indices.len())
+                .map(|k| [indices[0], indices[k - 1], indices[k]])
+                .collect(),
+            // points and lines have no surface
+            _ => Vec::new(),
+        };
+        let material = primitive.material().index().unwrap_or(default_material);
+        for mut face in faces {
+            if mirrored {
+                face.swap(1, 2);
+            }
+            let p = face.map(|i| positions[i]);
+            let mut t = match &normals {
+                Some(normals) => Triangle::with_normals(p, face.map(|i| normals[i])),
+                None => {
+                    missing_normals.push(triangles.len());
+                    Triangle::from_array(p)
+                }
+            };
+            t.material = material;
+            triangles.push(t);
+        }
+    }
+    for child in node.children() {
+        add_node(
+            &child,
+            transform,
+            buffers,
+            default_material,
+            triangles,
+            missing_normals,
+        )?;
+    }
+    Ok(())
+}
+
+// Liest die Dreiecke der Standardszene (bzw. der ersten Szene) einer .gltf- oder .glb-Datei in Weltkoordinaten.
+// load_buffer liefert den Inhalt separater Pufferdateien. Fehlende Normalen werden berechnet.
+pub fn parse_gltf(
+    data: &[u8],
+    load_buffer: &mut dyn FnMut(&str) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    let gltf = gltf::Gltf::from_slice(data)?;
+    let buffers = load_buffers(&gltf, load_buffer)?;
+    let mut materials: Vec<Material> = gltf.materials().map(|m| convert_material(&m)).collect();
+    // for primitives without material
+    let default_material = materials.len();
+    materials.push(Material::default_grey());
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or(anyhow!("glTF file without scene"))?;
+    let mut triangles = Vec::new();
+    let mut missing_normals = Vec::new();
+    for node in scene.nodes() {
+        add_node(
+            &node,
+            Mat4::IDENTITY,
+            &buffers,
+            default_material,
+            &mut triangles,
+            &mut missing_normals,
+        )?;
+    }
+    generate_missing_normals(&mut triangles, &missing_normals);
+    Ok((materials, triangles))
+}
+
+// Lädt eine .gltf- oder .glb-Datei, Pufferdateien werden relativ zu deren Verzeichnis gesucht
+pub fn load_gltf(path: &Path) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    let data = fs::read(path)?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_gltf(&data, &mut |uri| {
+        let buffer_path = base.join(uri);
+        fs::read(&buffer_path)
+            .with_context(|| format!("Cannot read glTF buffer {}", buffer_path.display()))
+    })
+}
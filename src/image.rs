@@ -0,0 +1,115 @@
+use crate::color::Color;
+use anyhow::anyhow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[test]
+fn test_write_ppm() {
+    let mut image = Image::new(2, 1);
+    image.set(0, 0, Color::new(1.0, 0.5, 0.0, 1.0));
+    image.set(1, 0, Color::new(2.0, -1.0, 0.2, 1.0));
+    let mut out: Vec<u8> = Vec::new();
+    image.write_ppm(&mut out).unwrap();
+    assert_eq!(out[..11], *b"P6\n2 1\n255\n");
+    assert_eq!(out[11..], [255, 128, 0, 255, 0, 51]);
+}
+
+#[test]
+fn test_write_png() {
+    let image = Image::new(3, 2);
+    let mut out: Vec<u8> = Vec::new();
+    image.write_png(&mut out).unwrap();
+    assert_eq!(
+        out[..8],
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+    );
+}
+
+#[test]
+fn test_save_unsupported() {
+    let path = std::env::temp_dir().join(format!("raytracer_{}.jpg", std::process::id()));
+    assert!(Image::new(1, 1).save(&path).is_err());
+    // nothing is created or overwritten
+    assert!(!path.exists());
+    assert!(matches!(format(Path::new("a.PNG")), Ok(Format::Png)));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Png,
+    Ppm,
+}
+
+// Bildformat anhand der Dateiendung, damit sich ein Tippfehler schon vor dem Rendern bemerkbar macht
+pub fn format(path: &Path) -> Result<Format, anyhow::Error> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension.to_lowercase().as_str() {
+        "png" => Ok(Format::Png),
+        "ppm" => Ok(Format::Ppm),
+        _ => Err(anyhow!(
+            "Unsupported image format: {} (expected .png or .ppm)",
+            path.display()
+        )),
+    }
+}
+
+// Pixelpuffer im Hauptspeicher, zeilenweise von links oben nach rechts unten
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::black(); (width * height) as usize],
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, c: Color) {
+        self.pixels[(y * self.width + x) as usize] = c;
+    }
+
+    // 8 Bit RGB ohne alpha, Farbwerte außerhalb von [0, 1] werden abgeschnitten
+    fn to_rgb8(&self) -> Vec<u8> {
+        let quantize = |f: f32| (f.clamp(0.0, 1.0) * 255.0).round() as u8;
+        self.pixels
+            .iter()
+            .flat_map(|c| [quantize(c.r), quantize(c.g), quantize(c.b)])
+            .collect()
+    }
+
+    // binary portable pixmap (P6)
+    pub fn write_ppm(&self, w: &mut impl Write) -> std::io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        w.write_all(&self.to_rgb8())
+    }
+
+    pub fn write_png(&self, w: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.to_rgb8())
+    }
+
+    // Speichert das Bild, das Format wird anhand der Dateiendung gewählt
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let format = format(path)?;
+        let mut w = BufWriter::new(File::create(path)?);
+        match format {
+            Format::Png => self.write_png(&mut w)?,
+            Format::Ppm => self.write_ppm(&mut w)?,
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
@@ -69,6 +69,7 @@ impl VertexArray {
     }
 
     /// Clear the current vertex array object binding.
+    #[allow(dead_code)]
     pub fn clear_binding() {
         unsafe { glBindVertexArray(0) }
     }
@@ -106,6 +107,7 @@ impl Buffer {
     }
 
     /// Clear the current vertex buffer binding for the given type.
+    #[allow(dead_code)]
     pub fn clear_binding(ty: BufferType) {
         unsafe { glBindBuffer(ty as GLenum, 0) }
     }
@@ -323,6 +325,7 @@ impl ShaderProgram {
 }
 
 /// The polygon display modes you can set.
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PolygonMode {
     /// Just show the points.
@@ -334,6 +337,7 @@ pub enum PolygonMode {
 }
 
 /// Sets the font and back polygon mode to the mode given.
+#[allow(dead_code)]
 pub fn polygon_mode(mode: PolygonMode) {
     unsafe { glPolygonMode(GL_FRONT_AND_BACK, mode as GLenum) };
 }
@@ -1,16 +1,365 @@
 use crate::color::Color;
+use crate::sampling::orthonormal_basis;
 use glam::f32::Vec3A;
 
+#[test]
+fn test_attenuation() {
+    let a = Attenuation {
+        konstant: 1.0,
+        linear: 0.0,
+        quadratisch: 1.0,
+    };
+    assert_eq!(a.factor(0.0), 1.0);
+    assert_eq!(a.factor(3.0), 0.1);
+}
+
+// Abschwächung des Lichts mit der Entfernung d: 1 / (konstant + linear * d + quadratisch * d²)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub konstant: f32,
+    pub linear: f32,
+    pub quadratisch: f32,
+}
+
+impl Attenuation {
+    // no attenuation at all
+    pub fn none() -> Self {
+        Self {
+            konstant: 1.0,
+            linear: 0.0,
+            quadratisch: 0.0,
+        }
+    }
+
+    pub fn factor(&self, d: f32) -> f32 {
+        1.0 / (self.konstant + self.linear * d + self.quadratisch * d * d)
+    }
+}
+
+#[test]
+fn test_shape_sample() {
+    let p = Vec3A::new(0.0, 5.0, 0.0);
+    let grid = (0..8).flat_map(|i| (0..8).map(move |j| ((i as f32 + 0.5) / 8.0, (j as f32 + 0.5) / 8.0)));
+    let rectangle = Shape::Rectangle {
+        center: Vec3A::new(1.0, 2.0, 3.0),
+        edge1: Vec3A::new(2.0, 0.0, 0.0),
+        edge2: Vec3A::new(0.0, 0.0, 4.0),
+    };
+    let disk = Shape::Disk {
+        center: Vec3A::ZERO,
+        normal: Vec3A::Y,
+        radius: 2.0,
+    };
+    let sphere = Shape::Sphere {
+        center: Vec3A::ZERO,
+        radius: 2.0,
+    };
+    let a = [Vec3A::ZERO, Vec3A::X, Vec3A::Z];
+    let b = [Vec3A::Y, Vec3A::Y + 3.0 * Vec3A::X, Vec3A::Y + 3.0 * Vec3A::Z];
+    // with a degenerate triangle in between, which is never chosen
+    let emissive = Shape::emissive(0, vec![a, [Vec3A::X; 3], b]);
+    let mut on_b = 0;
+    for (u, v) in grid {
+        let (q, n) = rectangle.sample(u, v, p);
+        assert_eq!(n, -Vec3A::Y);
+        assert!(q.y == 2.0 && (0.0..=2.0).contains(&q.x) && (1.0..=5.0).contains(&q.z));
+        let (q, _) = disk.sample(u, v, p);
+        assert!(q.y.abs() < 1e-6 && q.length() <= 2.0 + 1e-6);
+        // only the half facing p
+        let (q, n) = sphere.sample(u, v, p);
+        assert!((q.length() - 2.0).abs() < 1e-5 && q.y >= 0.0);
+        assert!((q / 2.0 - n).length() < 1e-5);
+        let (q, _) = emissive.sample(u, v, p);
+        assert!(q.x >= 0.0 && q.z >= 0.0 && q.x + q.z <= 3.0 + 1e-5);
+        assert!(q.y.abs() < 1e-5 || (q.y - 1.0).abs() < 1e-5);
+        if (q.y - 1.0).abs() < 1e-5 {
+            on_b += 1;
+        }
+    }
+    // b has 9 times the area of a
+    assert!((on_b as f32 / 64.0 - 0.9).abs() < 0.05);
+    assert_eq!(emissive.area(), 5.0);
+    assert_eq!(rectangle.area(), 8.0);
+}
+
+#[derive(Debug, PartialEq)]
 pub struct LightSource {
     pub pos: Vec3A,
     pub color: Color,
 }
 
-impl LightSource {
-    fn new() -> Self {
-        Self {
-            pos: Vec3A::new(0.0, 10.0, 0.0),
+// Anzahl der Schattenstrahlen zu einer Flächenlichtquelle, wenn die Szene keine angibt
+pub const AREA_LIGHT_SAMPLES: u32 = 16;
+
+// Leuchtende Fläche, die Farbe ist die Strahldichte jedes Punkts wie bei Scene::emission. Ebene Formen leuchten nur
+// zur Seite ihrer Normale, das Licht fällt mit 1 / d² statt mit der Abschwächung der Szene ab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaLight {
+    pub shape: Shape,
+    pub color: Color,
+    // Schattenstrahlen pro beleuchtetem Punkt
+    pub samples: u32,
+}
+
+// Form einer Flächenlichtquelle
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    // edges are the full side vectors starting at the corner center - (edge1 + edge2) / 2, lit side edge1 × edge2
+    Rectangle {
+        center: Vec3A,
+        edge1: Vec3A,
+        edge2: Vec3A,
+    },
+    Disk {
+        center: Vec3A,
+        normal: Vec3A,
+        radius: f32,
+    },
+    Sphere {
+        center: Vec3A,
+        radius: f32,
+    },
+    Triangle([Vec3A; 3]),
+    // all triangles of a material of the mesh, material is the index into Scene::materials, lit on both sides
+    Emissive {
+        material: usize,
+        triangles: Vec<[Vec3A; 3]>,
+        // cumulative areas of the triangles, see Shape::emissive
+        cdf: Vec<f32>,
+    },
+}
+
+fn triangle_area(t: &[Vec3A; 3]) -> f32 {
+    0.5 * (t[1] - t[0]).cross(t[2] - t[0]).length()
+}
+
+fn triangle_normal(t: &[Vec3A; 3]) -> Vec3A {
+    (t[1] - t[0]).cross(t[2] - t[0]).normalize()
+}
+
+// gleichverteilt auf dem Dreieck (Turk)
+fn triangle_point(t: &[Vec3A; 3], u: f32, v: f32) -> Vec3A {
+    let s = u.sqrt();
+    (1.0 - s) * t[0] + s * (1.0 - v) * t[1] + s * v * t[2]
+}
+
+impl Shape {
+    // Leuchtende Dreiecke eines Materials, die Flächen werden einmal aufsummiert, damit sample ein Dreieck per
+    // Binärsuche wählen kann
+    pub fn emissive(material: usize, triangles: Vec<[Vec3A; 3]>) -> Self {
+        let cdf = triangles
+            .iter()
+            .scan(0.0, |sum, t| {
+                *sum += triangle_area(t);
+                Some(*sum)
+            })
+            .collect();
+        Shape::Emissive {
+            material,
+            triangles,
+            cdf,
+        }
+    }
+
+    // Inhalt der Fläche, auf der sample die Punkte verteilt, bei der Kugel nur die Hälfte
+    pub fn area(&self) -> f32 {
+        match self {
+            Shape::Rectangle { edge1, edge2, .. } => edge1.cross(*edge2).length(),
+            Shape::Disk { radius, .. } => std::f32::consts::PI * radius * radius,
+            Shape::Sphere { radius, .. } => 2.0 * std::f32::consts::PI * radius * radius,
+            Shape::Triangle(t) => triangle_area(t),
+            Shape::Emissive { cdf, .. } => cdf.last().copied().unwrap_or(0.0),
+        }
+    }
+
+    // Gleichverteilter Punkt auf der Fläche für (u, v) aus dem Einheitsquadrat und die Normale dort. Bei der Kugel
+    // nur auf der p zugewandten Hälfte, da die andere Hälfte von p aus verdeckt ist.
+    pub fn sample(&self, u: f32, v: f32, p: Vec3A) -> (Vec3A, Vec3A) {
+        match self {
+            Shape::Rectangle {
+                center,
+                edge1,
+                edge2,
+            } => (
+                *center + (u - 0.5) * *edge1 + (v - 0.5) * *edge2,
+                edge1.cross(*edge2).normalize(),
+            ),
+            Shape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let (t, s) = orthonormal_basis(*normal);
+                let r = radius * u.sqrt();
+                let phi = 2.0 * std::f32::consts::PI * v;
+                (*center + r * (phi.cos() * t + phi.sin() * s), *normal)
+            }
+            Shape::Sphere { center, radius } => {
+                let z = 1.0 - 2.0 * u;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * std::f32::consts::PI * v;
+                let d = Vec3A::new(r * phi.cos(), r * phi.sin(), z);
+                let d = if d.dot(p - *center) < 0.0 { -d } else { d };
+                (*center + *radius * d, d)
+            }
+            Shape::Triangle(t) => (triangle_point(t, u, v), triangle_normal(t)),
+            Shape::Emissive { triangles, cdf, .. } => {
+                let total = match cdf.last() {
+                    Some(&total) if total > 0.0 => total,
+                    _ => return (p, Vec3A::ZERO),
+                };
+                // choose a triangle proportionally to its area and reuse u for the point on it, the last triangle
+                // with a positive area catches u * total rounding up to total
+                let x = u * total;
+                let i = cdf
+                    .partition_point(|&c| c <= x)
+                    .min(cdf.partition_point(|&c| c < total));
+                let start = if i == 0 { 0.0 } else { cdf[i - 1] };
+                let t = &triangles[i];
+                (triangle_point(t, ((x - start) / (cdf[i] - start)).min(1.0), v), triangle_normal(t))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_incident() {
+    let none = Attenuation::none();
+    let p = Vec3A::ZERO;
+    let sun = Light::Directional(DirectionalLight {
+        dir: Vec3A::new(0.0, -2.0, 0.0),
+        color: Color::white(),
+    });
+    let (l, distance, color) = sun.incident(0.5, 0.5, p, &none).unwrap();
+    assert_eq!((l, distance, color), (Vec3A::Y, f32::INFINITY, Color::white()));
+    let spot = |falloff| {
+        Light::Spot(SpotLight {
+            pos: Vec3A::new(0.0, 2.0, 0.0),
+            dir: -Vec3A::Y,
+            color: Color::white(),
+            angle: 30f32.to_radians(),
+            falloff,
+        })
+    };
+    let (l, distance, color) = spot(0.0).incident(0.5, 0.5, p, &none).unwrap();
+    assert_eq!((l, distance, color), (Vec3A::Y, 2.0, Color::white()));
+    // 45° off the axis is outside the 30° cone
+    assert!(spot(0.0)
+        .incident(0.5, 0.5, Vec3A::new(2.0, 0.0, 0.0), &none)
+        .is_none());
+    let (_, _, color) = spot(2.0)
+        .incident(0.5, 0.5, Vec3A::new(1.0, 0.0, 0.0), &none)
+        .unwrap();
+    // cos² of the angle to the axis
+    assert!((color.r - 0.8).abs() < 1e-5);
+    let point = Light::Point(LightSource {
+        pos: Vec3A::new(0.0, 3.0, 0.0),
+        color: Color::white(),
+    });
+    let quadratic = Attenuation {
+        quadratisch: 1.0,
+        ..none
+    };
+    let (_, _, color) = point.incident(0.5, 0.5, p, &quadratic).unwrap();
+    assert_eq!(color.r, 0.1);
+    assert_eq!(point.samples(), 1);
+    // a small square light 2 units above p, facing down, is seen under the solid angle area / d²
+    let square = |edge2| {
+        Light::Area(AreaLight {
+            shape: Shape::Rectangle {
+                center: Vec3A::new(0.0, 2.0, 0.0),
+                edge1: 0.01 * Vec3A::X,
+                edge2,
+            },
             color: Color::white(),
+            samples: 1,
+        })
+    };
+    let (_, _, color) = square(0.01 * Vec3A::Z).incident(0.5, 0.5, p, &quadratic).unwrap();
+    assert!((color.r - 1e-4 / (4.0 * std::f32::consts::PI)).abs() < 1e-9);
+    // the back side does not shine
+    assert!(square(-0.01 * Vec3A::Z).incident(0.5, 0.5, p, &none).is_none());
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DirectionalLight {
+    // Ausbreitungsrichtung des Lichts, von der Lichtquelle in die Szene
+    pub dir: Vec3A,
+    pub color: Color,
+}
+
+// Punktlichtquelle, die nur innerhalb eines Kegels um dir leuchtet
+#[derive(Debug, PartialEq)]
+pub struct SpotLight {
+    pub pos: Vec3A,
+    pub dir: Vec3A,
+    pub color: Color,
+    // halber Öffnungswinkel des Kegels im Bogenmaß
+    pub angle: f32,
+    // Exponent des Abfalls zum Rand wie GL_SPOT_EXPONENT, 0 leuchtet den Kegel gleichmäßig aus
+    pub falloff: f32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Light {
+    Point(LightSource),
+    // like the sun, without attenuation and with parallel shadows
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+    Area(AreaLight),
+}
+
+impl Light {
+    // Schattenstrahlen pro beleuchtetem Punkt
+    pub fn samples(&self) -> u32 {
+        match self {
+            Light::Area(light) => light.samples.max(1),
+            _ => 1,
+        }
+    }
+
+    // Richtung zum Licht, Entfernung und ankommende Farbe am Punkt p für den Abtastpunkt (u, v) einer Fläche, None
+    // wenn p außerhalb des Lichtkegels liegt. Die Entfernung begrenzt den Schattenstrahl.
+    pub fn incident(
+        &self,
+        u: f32,
+        v: f32,
+        p: Vec3A,
+        attenuation: &Attenuation,
+    ) -> Option<(Vec3A, f32, Color)> {
+        let towards = |pos: Vec3A, color: Color| {
+            let distance = (pos - p).length();
+            ((pos - p) / distance, distance, color * attenuation.factor(distance))
+        };
+        match self {
+            Light::Point(light) => Some(towards(light.pos, light.color)),
+            Light::Directional(light) => {
+                Some((-light.dir.normalize(), f32::INFINITY, light.color))
+            }
+            Light::Spot(light) => {
+                let (l, distance, color) = towards(light.pos, light.color);
+                let cos = (-l).dot(light.dir.normalize());
+                if cos < light.angle.cos() {
+                    return None;
+                }
+                Some((l, distance, color * cos.max(0.0).powf(light.falloff)))
+            }
+            Light::Area(light) => {
+                let (q, normal) = light.shape.sample(u, v, p);
+                let distance = (q - p).length();
+                let l = (q - p) / distance;
+                let cos = match light.shape {
+                    Shape::Emissive { .. } => normal.dot(l).abs(),
+                    _ => normal.dot(-l),
+                };
+                if cos <= 0.0 {
+                    return None;
+                }
+                // Strahldichte über die Fläche integriert, 1 / π gehört zum diffusen Anteil, damit das Ergebnis
+                // zum Treffer eines leuchtenden Dreiecks in der Pfadverfolgung passt
+                let scale = light.shape.area() * cos / (std::f32::consts::PI * distance * distance);
+                Some((l, distance, light.color * scale))
+            }
         }
     }
 }
@@ -2,144 +2,104 @@
 #![allow(unused_imports)]
 #![allow(clippy::single_match)]
 #![allow(clippy::zero_ptr)]
-// https://raw.githubusercontent.com/rust-tutorials/learn-opengl/main/examples/002-triangle-arrays2.rs
 
+mod accelerator;
 mod bounding_box;
+mod bvh;
 mod camera;
+mod cli;
 mod color;
+mod dtd;
+mod file_export;
 mod file_import;
+mod gltf_import;
+mod image;
+#[cfg(feature = "preview")]
 mod learn;
 mod light_source;
 mod material;
+mod mesh;
+mod mesh_processing;
+mod obj;
+mod path_tracer;
+mod ply;
+#[cfg(feature = "preview")]
+mod preview;
+mod ray;
+mod render;
+mod sampling;
 mod scene;
+mod scene_error;
+mod stl;
 mod triangle;
-
-use crate::file_import::parse_scene;
-use std::fs;
-
-use crate::learn::*;
-use beryllium::*;
-
-const WINDOW_TITLE: &str = "Raytracer";
-
-use beryllium::*;
-use core::{
-    convert::{TryFrom, TryInto},
-    mem::{size_of, size_of_val},
-};
-use ogl33::*;
-use glam::f32::Mat4;
-
-type Vertex = [f32; 3];
-
-//const VERTICES: [Vertex; 3] = [[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.0, 0.5, 0.0]];
-
-const VERT_SHADER: &str = r#"#version 330 core
-  uniform mat4 transform;
-
-  layout (location = 0) in vec3 pos;
-
-  void main() {
-    gl_Position = transform * vec4(pos,1.0);
-  }
-"#;
-
-const FRAG_SHADER: &str = r#"#version 330 core
-  out vec4 final_color;
-
-  void main() {
-    final_color = vec4(1.0, 0.5, 0.2, 1.0);
-  }
-"#;
-
-fn main() {
-    let xml = fs::read_to_string("scene/abgabeszene.xml").unwrap();
-    let scene = parse_scene(&xml).unwrap();
-    let mut vertices: Vec<f32> = Vec::new();
-    // todo: use flat map
-    for t in scene.triangles {
-        for p in t.p {
-            vertices.push(p.x);
-            vertices.push(-p.y);
-            vertices.push(p.z);
+mod whitted;
+
+use crate::cli::{parse_args, Mode, Options, USAGE};
+use crate::file_export::{save_scene, save_triangulation};
+use crate::file_import::load_scene;
+use crate::mesh::Mesh;
+use crate::mesh_processing::{compute_normals, default_tolerance};
+use crate::render::{render_with_samples, sample_image};
+use crate::scene::Scene;
+use std::path::Path;
+
+// Rendert ohne Fenster auf der CPU und speichert das Ergebnis als PNG oder PPM
+fn render_to_file(scene: &Scene, options: &Options, output: &Path) -> Result<(), anyhow::Error> {
+    let settings = options.settings(scene.width, scene.height);
+    let (image, samples) = render_with_samples(scene, &settings, &mut |done, total| {
+        eprint!("\rRendering tile {}/{}", done, total);
+        if done == total {
+            eprintln!();
         }
+    });
+    if let Some(map) = &options.sample_map {
+        let max = match settings.threshold {
+            Some(_) => settings.max_samples.max(settings.samples),
+            None => settings.samples,
+        };
+        sample_image(&samples, settings.width, settings.height, max).save(map)?;
     }
-    //vertices.extend([-0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.0, 0.5, 0.0].iter().cloned()); // test
+    image.save(output)
+}
 
-    let sdl = SDL::init(InitFlags::Everything).expect("couldn't start SDL");
-    sdl.gl_set_attribute(SdlGlAttr::MajorVersion, 3).unwrap();
-    sdl.gl_set_attribute(SdlGlAttr::MinorVersion, 3).unwrap();
-    sdl.gl_set_attribute(SdlGlAttr::Profile, GlProfile::Core)
-        .unwrap();
-    #[cfg(target_os = "macos")]
-    {
-        sdl.gl_set_attribute(SdlGlAttr::Flags, ContextFlag::ForwardCompatible)
-            .unwrap();
-    }
+#[cfg(feature = "preview")]
+fn show(scene: &Scene) -> Result<(), anyhow::Error> {
+    preview::show(scene);
+    Ok(())
+}
 
-    let win = sdl
-        .create_gl_window(
-            WINDOW_TITLE,
-            WindowPosition::Centered,
-            800,
-            600,
-            WindowFlags::Shown,
-        )
-        .expect("couldn't make a window and context");
-    win.set_swap_interval(SwapInterval::Vsync);
+#[cfg(not(feature = "preview"))]
+fn show(_scene: &Scene) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!(
+        "built without the preview feature, please specify an output file"
+    ))
+}
 
-    unsafe {
-        load_gl_with(|f_name| win.get_proc_address(f_name));
+fn main() -> Result<(), anyhow::Error> {
+    let options = match parse_args(std::env::args().skip(1))? {
+        Some(options) => options,
+        None => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let mut scene = load_scene(&options.scene, options.triangulation.as_deref())?;
+    if let Some(crease_angle) = options.crease_angle {
+        let mut triangles: Vec<_> = scene.mesh.triangles().collect();
+        let tolerance = default_tolerance(&triangles);
+        compute_normals(&mut triangles, tolerance, crease_angle, options.normal_weighting);
+        scene.mesh = Mesh::from_triangles(&triangles);
     }
-
-    clear_color(0.2, 0.3, 0.3, 1.0);
-
-    let vao = VertexArray::new().expect("Couldn't make a VAO");
-    vao.bind();
-
-    let vbo = Buffer::new().expect("Couldn't make a VBO");
-    vbo.bind(BufferType::Array);
-
-    buffer_data(
-        BufferType::Array,
-        bytemuck::cast_slice(&vertices),
-        GL_STATIC_DRAW,
-    );
-
-    unsafe {
-        glVertexAttribPointer(
-            0,
-            3,
-            GL_FLOAT,
-            GL_FALSE,
-            size_of::<Vertex>().try_into().unwrap(),
-            0 as *const _,
-        );
-        glEnableVertexAttribArray(0);
+    if let Some(export) = &options.export {
+        save_triangulation(export, &scene.materials, &scene.mesh)?;
     }
-
-    let shader_program = ShaderProgram::from_vert_frag(VERT_SHADER, FRAG_SHADER).unwrap();
-    shader_program.use_program();
-
-    'main_loop: loop {
-        // handle events this frame
-        while let Some(event) = sdl.poll_events().and_then(Result::ok) {
-            match event {
-                Event::Quit(_) => break 'main_loop,
-                _ => (),
-            }
-        }
-        //let time = sdl.get_ticks() as f32 / 2000.0_f32;
-        //let transform = Mat4::from_rotation_z(time);
-        let transform = Mat4::perspective_lh(1.0,16.0/9.0,scene.camera.clippingNear,scene.camera.clippingFar);
-        // and then draw!
-        unsafe {
-            glClear(GL_COLOR_BUFFER_BIT);
-            let transform_name = null_str!("transform").as_ptr().cast();
-            let transform_loc = glGetUniformLocation(shader_program.0, transform_name);
-            glUniformMatrix4fv(transform_loc, 1, GL_FALSE, &transform.to_cols_array()[0]);
-            glDrawArrays(GL_TRIANGLES, 0, (3 * vertices.len()).try_into().unwrap());
-        }
-        win.swap_window();
+    if let Some(path) = &options.save_scene {
+        save_scene(&scene, path)?;
+    }
+    scene.build_accelerator(options.accelerator);
+    match &options.mode {
+        Mode::Render(output) => render_to_file(&scene, &options, output),
+        Mode::Preview => show(&scene),
+        Mode::None => Ok(()),
     }
 }
@@ -1,18 +1,52 @@
 use crate::color::Color;
 
+#[derive(Debug, PartialEq)]
 pub struct Material {
     pub name: String,
     pub ambient: Color,
     pub diffus: Color,
     pub spiegelnd: Color,
     pub glanz: f32,
+    // Brechungsindex für transparente Materialien, Glas hat etwa 1.5
+    pub brechungsindex: f32,
 }
 
+pub const BRECHUNGSINDEX_GLAS: f32 = 1.5;
+// Name des Materials für importierte Dreiecke ohne Materialangabe
+pub const DEFAULT_MATERIAL: &str = "default";
+
 impl Material {
+    // matt, nicht spiegelnd und undurchsichtig
+    pub fn new(name: &str, color: Color) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: color,
+            diffus: color,
+            spiegelnd: Color::new(0.0, 0.0, 0.0, 1.0),
+            glanz: 1.0,
+            brechungsindex: BRECHUNGSINDEX_GLAS,
+        }
+    }
+
+    // matt hellgrau wie der Standardwert für Kd in MTL-Dateien
+    pub fn default_grey() -> Self {
+        Self::new(DEFAULT_MATERIAL, Color::new(0.8, 0.8, 0.8, 1.0))
+    }
+
+    #[allow(non_snake_case)]
     pub fn isSpiegelnd(&self) -> bool {
         self.spiegelnd.a != 1.0
     }
+    #[allow(non_snake_case)]
     pub fn isTransparent(&self) -> bool {
         self.diffus.a != 1.0
     }
+    // Anteil des gespiegelten Lichts
+    pub fn reflectance(&self) -> f32 {
+        1.0 - self.spiegelnd.a
+    }
+    // Anteil des durchgelassenen Lichts
+    pub fn transparency(&self) -> f32 {
+        1.0 - self.diffus.a
+    }
 }
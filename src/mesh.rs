@@ -0,0 +1,108 @@
+// Indiziertes Dreiecksnetz: Eckpunkte werden von allen angrenzenden Dreiecken gemeinsam genutzt
+
+use crate::ray::Ray;
+use crate::triangle::{self, Triangle};
+use glam::f32::Vec3A;
+use std::collections::HashMap;
+
+#[test]
+fn test_from_triangles() {
+    let n = Vec3A::new(0.0, 0.0, 1.0);
+    let mut a = Triangle::with_normals([Vec3A::ZERO, Vec3A::X, Vec3A::Y], [n; 3]);
+    let mut b = Triangle::with_normals([Vec3A::X, Vec3A::ONE, Vec3A::Y], [n; 3]);
+    a.material = 1;
+    b.material = 2;
+    // same position with a different normal is a separate vertex
+    b.n[0] = Vec3A::X;
+    let mesh = Mesh::from_triangles(&[a.clone(), b.clone()]);
+    assert_eq!(mesh.len(), 2);
+    assert_eq!(mesh.positions.len(), 5);
+    assert_eq!(mesh.indices, vec![[0, 1, 2], [3, 4, 2]]);
+    for (original, t) in [a, b].iter().zip(mesh.triangles()) {
+        assert_eq!(
+            (t.p, t.n, t.material),
+            (original.p, original.n, original.material)
+        );
+    }
+    assert_eq!(mesh.points(1), [Vec3A::X, Vec3A::ONE, Vec3A::Y]);
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Mesh {
+    // ein Eckpunkt besteht aus Position und Normale mit demselben Index
+    pub positions: Vec<Vec3A>,
+    pub normals: Vec<Vec3A>,
+    // three vertex indices per face, counter clockwise
+    pub indices: Vec<[u32; 3]>,
+    // per face index into Scene::materials
+    pub materials: Vec<usize>,
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Fasst Eckpunkte mit bitweise gleicher Position und Normale zusammen
+    pub fn from_triangles(triangles: &[Triangle]) -> Self {
+        let mut mesh = Self::new();
+        let mut vertex_ids: HashMap<[u32; 6], u32> = HashMap::new();
+        for t in triangles {
+            let mut face = [0; 3];
+            for (id, (p, n)) in face.iter_mut().zip(t.p.into_iter().zip(t.n)) {
+                let key = [p.x, p.y, p.z, n.x, n.y, n.z].map(f32::to_bits);
+                *id = *vertex_ids.entry(key).or_insert_with(|| {
+                    mesh.positions.push(p);
+                    mesh.normals.push(n);
+                    (mesh.positions.len() - 1) as u32
+                });
+            }
+            mesh.indices.push(face);
+            mesh.materials.push(t.material);
+        }
+        mesh
+    }
+
+    // Hängt ein Dreieck mit eigenen Eckpunkten an
+    #[cfg(test)]
+    pub fn push(&mut self, t: &Triangle) {
+        let first = self.positions.len() as u32;
+        self.positions.extend(t.p);
+        self.normals.extend(t.n);
+        self.indices.push([first, first + 1, first + 2]);
+        self.materials.push(t.material);
+    }
+
+    // Anzahl der Dreiecke
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn points(&self, face: usize) -> [Vec3A; 3] {
+        self.indices[face].map(|i| self.positions[i as usize])
+    }
+
+    // Dreieck face als eigenständiges Triangle mit Kopien der Eckpunkte
+    pub fn triangle(&self, face: usize) -> Triangle {
+        let [a, b, c] = self.indices[face].map(|i| i as usize);
+        let mut t = Triangle::with_normals(
+            [self.positions[a], self.positions[b], self.positions[c]],
+            [self.normals[a], self.normals[b], self.normals[c]],
+        );
+        t.material = self.materials[face];
+        t
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        (0..self.len()).map(|face| self.triangle(face))
+    }
+
+    // Schnitt mit dem Dreieck face, siehe Triangle::intersect
+    pub fn intersect(&self, face: usize, ray: &Ray) -> Option<(f32, f32, f32)> {
+        triangle::intersect(&self.points(face), ray)
+    }
+}
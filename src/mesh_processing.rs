@@ -0,0 +1,229 @@
+// Verschweißen von Eckpunkten und Berechnung weicher Eckpunktnormalen für Dreiecksnetze ohne (brauchbare) Normalen
+
+use crate::triangle::Triangle;
+use anyhow::anyhow;
+use glam::f32::Vec3A;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// default weld tolerance relative to the diagonal of the mesh's bounding box
+pub const RELATIVE_WELD_TOLERANCE: f32 = 1e-6;
+// Knickwinkel für importierte Netze ohne Normalen, flachere Kanten werden geglättet
+pub const DEFAULT_CREASE_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+
+#[cfg(test)]
+// two unit right triangles sharing the edge from (0,0,0) to (0,1,0), folded by the given angle in degrees
+fn fold(angle: f32) -> Vec<Triangle> {
+    let (s, c) = angle.to_radians().sin_cos();
+    vec![
+        Triangle::new(Vec3A::ZERO, Vec3A::X, Vec3A::Y),
+        // slightly displaced copy of the shared edge, as produced by exporters writing each triangle separately
+        Triangle::new(Vec3A::new(0.0, 1e-7, 0.0), Vec3A::Y, Vec3A::new(-c, 0.0, s)),
+    ]
+}
+
+#[test]
+fn test_weld() {
+    let welded = weld(&fold(90.0), 1e-5);
+    assert_eq!(welded.positions.len(), 4);
+    assert_eq!(welded.faces, vec![[0, 1, 2], [0, 2, 3]]);
+    let adjacency = welded.vertex_faces();
+    assert_eq!(adjacency[0], vec![0, 1]);
+    assert_eq!(adjacency[3], vec![1]);
+    // without tolerance the displaced vertex stays separate
+    assert_eq!(weld(&fold(90.0), 0.0).positions.len(), 5);
+}
+
+#[test]
+fn test_compute_normals() {
+    // a 90° fold is a crease for a 60° threshold
+    let mut sharp = fold(90.0);
+    compute_normals(&mut sharp, 1e-5, 60f32.to_radians(), Weighting::Angle);
+    assert!((sharp[0].n[0] - Vec3A::Z).length() < 1e-6);
+    assert!((sharp[1].n[1] - sharp[1].normal()).length() < 1e-6);
+    // a 30° fold is smoothed, the shared edge gets the average of both face normals
+    for weighting in [Weighting::Angle, Weighting::Area] {
+        let mut smooth = fold(30.0);
+        compute_normals(&mut smooth, 1e-5, 60f32.to_radians(), weighting);
+        let average = (smooth[0].normal() + smooth[1].normal()).normalize();
+        assert!((smooth[0].n[2] - average).length() < 1e-5);
+        assert!((smooth[1].n[1] - average).length() < 1e-5);
+        // the outer vertices only belong to one face
+        assert!((smooth[0].n[1] - Vec3A::Z).length() < 1e-6);
+    }
+    assert_eq!("area".parse::<Weighting>().unwrap(), Weighting::Area);
+    assert!("uniform".parse::<Weighting>().is_err());
+}
+
+// Dreiecksnetz mit gemeinsam genutzten Eckpunkten
+pub struct Welded {
+    pub positions: Vec<Vec3A>,
+    // one index triple into positions per input triangle
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl Welded {
+    // Adjazenz: Indizes aller Dreiecke, die den jeweiligen Eckpunkt verwenden
+    pub fn vertex_faces(&self) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::new(); self.positions.len()];
+        for (f, face) in self.faces.iter().enumerate() {
+            for &v in face {
+                if adjacency[v].last() != Some(&f) {
+                    adjacency[v].push(f);
+                }
+            }
+        }
+        adjacency
+    }
+}
+
+// Fasst Eckpunkte, die weniger als tolerance voneinander entfernt sind, zu einem Eckpunkt zusammen
+pub fn weld(triangles: &[Triangle], tolerance: f32) -> Welded {
+    // hash grid with cells of size tolerance, so that close points are in the same or a neighbouring cell
+    let cell_size = if tolerance > 0.0 { tolerance } else { 1.0 };
+    let cell = |p: Vec3A| {
+        let c = (p / cell_size).floor();
+        (c.x as i64, c.y as i64, c.z as i64)
+    };
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    let mut positions: Vec<Vec3A> = Vec::new();
+    let mut faces = Vec::with_capacity(triangles.len());
+    for t in triangles {
+        let mut face = [0; 3];
+        for (corner, &p) in t.p.iter().enumerate() {
+            let (x, y, z) = cell(p);
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        for &v in grid
+                            .get(&(x.wrapping_add(dx), y.wrapping_add(dy), z.wrapping_add(dz)))
+                            .into_iter()
+                            .flatten()
+                        {
+                            if positions[v].distance(p) <= tolerance {
+                                found = Some(v);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            face[corner] = found.unwrap_or_else(|| {
+                positions.push(p);
+                grid.entry((x, y, z)).or_default().push(positions.len() - 1);
+                positions.len() - 1
+            });
+        }
+        faces.push(face);
+    }
+    Welded { positions, faces }
+}
+
+// Weld tolerance for the given triangles based on RELATIVE_WELD_TOLERANCE
+pub fn default_tolerance(triangles: &[Triangle]) -> f32 {
+    let mut min = Vec3A::splat(f32::INFINITY);
+    let mut max = Vec3A::splat(f32::NEG_INFINITY);
+    for p in triangles.iter().flat_map(|t| t.p) {
+        min = min.min(p);
+        max = max.max(p);
+    }
+    if triangles.is_empty() {
+        0.0
+    } else {
+        min.distance(max) * RELATIVE_WELD_TOLERANCE
+    }
+}
+
+// Gewichtung der Flächennormalen beim Mitteln an einem Eckpunkt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    // by triangle area, large triangles dominate
+    Area,
+    // by the triangle's interior angle at the vertex, independent of the tessellation
+    Angle,
+}
+
+impl FromStr for Weighting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "area" => Ok(Self::Area),
+            "angle" => Ok(Self::Angle),
+            _ => Err(anyhow!(
+                "Unknown normal weighting: {} (expected area or angle)",
+                s
+            )),
+        }
+    }
+}
+
+fn corner_angle(t: &Triangle, corner: usize) -> f32 {
+    let p = t.p[corner];
+    let a = (t.p[(corner + 1) % 3] - p).normalize_or_zero();
+    let b = (t.p[(corner + 2) % 3] - p).normalize_or_zero();
+    a.dot(b).clamp(-1.0, 1.0).acos()
+}
+
+// Ersetzt die Eckpunktnormalen durch gewichtete Mittel der Flächennormalen aller angrenzenden Dreiecke. Dreiecke,
+// deren Normalen um mehr als crease_angle (Bogenmaß) von der des jeweiligen Dreiecks abweichen, werden nicht
+// einbezogen, so dass Kanten scharf bleiben.
+pub fn compute_normals(
+    triangles: &mut [Triangle],
+    tolerance: f32,
+    crease_angle: f32,
+    weighting: Weighting,
+) {
+    let welded = weld(triangles, tolerance);
+    let adjacency = welded.vertex_faces();
+    let face_normals: Vec<Vec3A> = triangles
+        .iter()
+        .map(|t| (t.p[1] - t.p[0]).cross(t.p[2] - t.p[0]))
+        .collect();
+    let unit_normals: Vec<Vec3A> = face_normals.iter().map(|n| n.normalize_or_zero()).collect();
+    let cos_crease = crease_angle.cos();
+    let mut normals = vec![[Vec3A::ZERO; 3]; triangles.len()];
+    for (f, face) in welded.faces.iter().enumerate() {
+        for corner in 0..3 {
+            let mut sum = Vec3A::ZERO;
+            for &g in &adjacency[face[corner]] {
+                if g != f && unit_normals[f].dot(unit_normals[g]) < cos_crease {
+                    continue;
+                }
+                let weight = match weighting {
+                    // the cross product's length is twice the area
+                    Weighting::Area => face_normals[g],
+                    Weighting::Angle => {
+                        let g_corner = welded.faces[g].iter().position(|&v| v == face[corner]);
+                        unit_normals[g] * g_corner.map_or(0.0, |c| corner_angle(&triangles[g], c))
+                    }
+                };
+                sum += weight;
+            }
+            normals[f][corner] = sum.try_normalize().unwrap_or(unit_normals[f]);
+        }
+    }
+    for (t, n) in triangles.iter_mut().zip(normals) {
+        t.n = n;
+    }
+}
+
+// Berechnet weiche Normalen für die Dreiecke mit den Indizes faces, etwa für importierte Dreiecke ohne Normalen.
+// Die übrigen Dreiecke bleiben unverändert und werden auch nicht in die Mittelung einbezogen.
+pub fn generate_missing_normals(triangles: &mut [Triangle], faces: &[usize]) {
+    if faces.is_empty() {
+        return;
+    }
+    let mut subset: Vec<Triangle> = faces.iter().map(|&f| triangles[f].clone()).collect();
+    let tolerance = default_tolerance(&subset);
+    compute_normals(
+        &mut subset,
+        tolerance,
+        DEFAULT_CREASE_ANGLE,
+        Weighting::Angle,
+    );
+    for (&f, t) in faces.iter().zip(subset) {
+        triangles[f].n = t.n;
+    }
+}
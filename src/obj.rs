@@ -0,0 +1,410 @@
+// Import und Export von Wavefront OBJ mit Materialien aus MTL-Dateien
+
+use crate::color::Color;
+use crate::material::{Material, BRECHUNGSINDEX_GLAS, DEFAULT_MATERIAL};
+use crate::mesh::Mesh;
+use crate::mesh_processing::generate_missing_normals;
+use crate::triangle::Triangle;
+use anyhow::{anyhow, Context};
+use glam::f32::Vec3A;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_parse_mtl() {
+    const MTL: &str = "# comment
+newmtl glas
+Ka 0.1 0.1 0.1
+Kd 0.2 0.4 0.6
+Ks 0.5 0.5 0.5
+Ns 20
+d 0.25
+Ni 1.33
+illum 7
+
+newmtl matt
+Kd 1 0 0
+Tr 0.0
+";
+    let materials = parse_mtl(MTL).unwrap();
+    assert_eq!(materials.len(), 2);
+    let glas = &materials[0];
+    assert_eq!(glas.name, "glas");
+    assert_eq!(glas.ambient, Color::new(0.1, 0.1, 0.1, 1.0));
+    assert_eq!(glas.diffus, Color::new(0.2, 0.4, 0.6, 0.25));
+    // illum 7 enables ray traced reflection with strength Ks, the renderers weight it by spiegelnd * reflectance()
+    assert_eq!(glas.spiegelnd, Color::new(0.5, 0.5, 0.5, 0.0));
+    assert!(glas.isSpiegelnd());
+    assert_eq!(glas.spiegelnd * glas.reflectance(), Color::new(0.5, 0.5, 0.5, 0.0));
+    assert_eq!((glas.glanz, glas.brechungsindex), (20.0, 1.33));
+    let matt = &materials[1];
+    assert_eq!(matt.ambient, Color::new(1.0, 0.0, 0.0, 1.0));
+    assert!(!matt.isSpiegelnd() && !matt.isTransparent());
+    assert!(parse_mtl("Kd 1 1 1").is_err());
+    assert!(parse_mtl("newmtl a\nKd 1 x 1").is_err());
+}
+
+#[test]
+fn test_parse_obj() {
+    const OBJ: &str = "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vn 0 0 1
+vt 0.5 0.5
+f 1 2 3 4
+usemtl rot
+f 1//1 2/1/1 -1/1/1
+f 1/1 2/1 5/1
+";
+    let mut materials = vec![Material::new("rot", Color::new(1.0, 0.0, 0.0, 1.0))];
+    let triangles = parse_obj(OBJ, &mut materials, &mut |_| unreachable!()).unwrap();
+    // the quad is split into a fan of two triangles
+    assert_eq!(triangles.len(), 4);
+    assert_eq!(
+        triangles[1].p,
+        [Vec3A::ZERO, Vec3A::ONE - Vec3A::Z, Vec3A::Y]
+    );
+    assert_eq!(materials[triangles[0].material].name, DEFAULT_MATERIAL);
+    assert_eq!(triangles[2].material, 0);
+    assert_eq!(triangles[2].p[2], Vec3A::Z);
+    assert_eq!(triangles[2].n, [Vec3A::Z; 3]);
+    // generated normals are normalized, a triangle without neighbours gets its face normal
+    assert_eq!(triangles[0].n[0], Vec3A::Z);
+    assert_eq!(triangles[3].n[1], -Vec3A::Y);
+
+    let error = |obj: &str| {
+        parse_obj(obj, &mut Vec::new(), &mut |_| Ok(String::new()))
+            .unwrap_err()
+            .to_string()
+    };
+    assert_eq!(error("v 0 0 0\nf 1 1 2"), "line 2: invalid vertex index 2");
+    assert_eq!(error("usemtl gold"), "line 1: Unknown material: gold");
+    assert_eq!(error("v 0 0\n"), "line 1: expected 3 numbers");
+}
+
+fn parse_numbers<const N: usize>(args: &[&str]) -> Result<[f32; N], anyhow::Error> {
+    if args.len() < N {
+        return Err(anyhow!("expected {} numbers", N));
+    }
+    let mut numbers = [0.0; N];
+    for (n, a) in numbers.iter_mut().zip(args) {
+        *n = a.parse().map_err(|_| anyhow!("invalid number {}", a))?;
+    }
+    Ok(numbers)
+}
+
+fn parse_rgb(args: &[&str]) -> Result<Color, anyhow::Error> {
+    let [r, g, b] = parse_numbers(args)?;
+    Ok(Color::new(r, g, b, 1.0))
+}
+
+// Eine MTL-Materialbeschreibung, wird beim nächsten newmtl bzw. am Dateiende zu einem Material
+struct MtlMaterial {
+    name: String,
+    ka: Option<Color>,
+    kd: Color,
+    ks: Color,
+    ns: f32,
+    d: f32,
+    ni: f32,
+    illum: u32,
+}
+
+impl MtlMaterial {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ka: None,
+            kd: Color::new(0.8, 0.8, 0.8, 1.0),
+            ks: Color::black(),
+            ns: 1.0,
+            d: 1.0,
+            ni: BRECHUNGSINDEX_GLAS,
+            illum: 2,
+        }
+    }
+
+    fn into_material(self) -> Material {
+        let mut spiegelnd = self.ks;
+        // illumination models 3 to 7 enable ray traced reflections, Ks alone is their strength
+        if (3..=7).contains(&self.illum) && self.ks.r.max(self.ks.g).max(self.ks.b) > 0.0 {
+            spiegelnd.a = 0.0;
+        }
+        Material {
+            name: self.name,
+            // without Ka the diffuse color is used, like Material::new
+            ambient: self.ka.unwrap_or(self.kd),
+            diffus: Color {
+                a: self.d,
+                ..self.kd
+            },
+            spiegelnd,
+            glanz: self.ns,
+            brechungsindex: self.ni,
+        }
+    }
+}
+
+// Liest die Materialien einer MTL-Datei: Ka, Kd, Ks und Ns werden auf ambient, diffus, spiegelnd und glanz
+// abgebildet, d bzw. Tr auf die Transparenz und Ni auf den Brechungsindex
+pub fn parse_mtl(mtl: &str) -> Result<Vec<Material>, anyhow::Error> {
+    let mut materials = Vec::new();
+    let mut current: Option<MtlMaterial> = None;
+    for (number, line) in mtl.lines().enumerate() {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let (&keyword, args) = match args.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        let result = (|| {
+            if keyword.starts_with('#') {
+                return Ok(());
+            }
+            if keyword == "newmtl" {
+                materials.extend(current.take().map(MtlMaterial::into_material));
+                current = Some(MtlMaterial::new(&args.join(" ")));
+                return Ok(());
+            }
+            let m = match current.as_mut() {
+                Some(m) => m,
+                None => return Err(anyhow!("{} before newmtl", keyword)),
+            };
+            match keyword {
+                "Ka" => m.ka = Some(parse_rgb(args)?),
+                "Kd" => m.kd = parse_rgb(args)?,
+                "Ks" => m.ks = parse_rgb(args)?,
+                "Ns" => m.ns = parse_numbers::<1>(args)?[0],
+                "d" => m.d = parse_numbers::<1>(args)?[0],
+                "Tr" => m.d = 1.0 - parse_numbers::<1>(args)?[0],
+                "Ni" => m.ni = parse_numbers::<1>(args)?[0],
+                "illum" => m.illum = parse_numbers::<1>(args)?[0] as u32,
+                // texture maps and other statements are not supported
+                _ => {}
+            }
+            Ok(())
+        })();
+        result.map_err(|e: anyhow::Error| anyhow!("line {}: {}", number + 1, e))?;
+    }
+    materials.extend(current.map(MtlMaterial::into_material));
+    Ok(materials)
+}
+
+// 1-based index, negative values count backwards from the last element defined so far
+fn resolve_index(index: &str, len: usize) -> Result<usize, anyhow::Error> {
+    let invalid = || anyhow!("invalid vertex index {}", index);
+    let i: i64 = index.parse().map_err(|_| invalid())?;
+    let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(invalid());
+    }
+    Ok(resolved as usize)
+}
+
+// Liest die Dreiecke einer OBJ-Datei, Polygone werden als Fächer trianguliert. Materialien aus mtllib und usemtl werden
+// an materials angehängt, load_mtl liefert den Inhalt einer mtllib-Datei. Fehlen Normalen, werden sie berechnet.
+pub fn parse_obj(
+    obj: &str,
+    materials: &mut Vec<Material>,
+    load_mtl: &mut dyn FnMut(&str) -> Result<String, anyhow::Error>,
+) -> Result<Vec<Triangle>, anyhow::Error> {
+    let mut material_ids: HashMap<String, usize> = materials
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (m.name.clone(), i))
+        .collect();
+    let mut positions: Vec<Vec3A> = Vec::new();
+    let mut normals: Vec<Vec3A> = Vec::new();
+    let mut triangles: Vec<Triangle> = Vec::new();
+    let mut missing_normals: Vec<usize> = Vec::new();
+    let mut material: Option<usize> = None;
+    for (number, line) in obj.lines().enumerate() {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let (&keyword, args) = match args.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        let result = (|| {
+            match keyword {
+                "v" => positions.push(Vec3A::from(parse_numbers::<3>(args)?)),
+                "vn" => normals.push(Vec3A::from(parse_numbers::<3>(args)?)),
+                "mtllib" => {
+                    let file = args.join(" ");
+                    let mtl = load_mtl(&file)?;
+                    for m in parse_mtl(&mtl).with_context(|| format!("in {}", file))? {
+                        // a later definition with the same name replaces the earlier one
+                        match material_ids.get(&m.name) {
+                            Some(&i) => materials[i] = m,
+                            None => {
+                                material_ids.insert(m.name.clone(), materials.len());
+                                materials.push(m);
+                            }
+                        }
+                    }
+                }
+                "usemtl" => {
+                    let name = args.join(" ");
+                    material = Some(
+                        *material_ids
+                            .get(&name)
+                            .ok_or(anyhow!("Unknown material: {}", name))?,
+                    );
+                }
+                "f" => {
+                    if args.len() < 3 {
+                        return Err(anyhow!("face with less than 3 vertices"));
+                    }
+                    let mut corners: Vec<(Vec3A, Option<Vec3A>)> = Vec::with_capacity(args.len());
+                    for corner in args {
+                        // v, v/vt, v//vn or v/vt/vn
+                        let mut indices = corner.split('/');
+                        let p = positions[resolve_index(indices.next().unwrap(), positions.len())?];
+                        let n = match indices.nth(1) {
+                            Some(n) if !n.is_empty() => {
+                                Some(normals[resolve_index(n, normals.len())?])
+                            }
+                            _ => None,
+                        };
+                        corners.push((p, n));
+                    }
+                    let material = *material.get_or_insert_with(|| {
+                        *material_ids
+                            .entry(DEFAULT_MATERIAL.to_string())
+                            .or_insert_with(|| {
+                                materials.push(Material::default_grey());
+                                materials.len() - 1
+                            })
+                    });
+                    for i in 1..corners.len() - 1 {
+                        let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                        let mut t = match (a.1, b.1, c.1) {
+                            (Some(na), Some(nb), Some(nc)) => {
+                                Triangle::with_normals([a.0, b.0, c.0], [na, nb, nc])
+                            }
+                            _ => {
+                                missing_normals.push(triangles.len());
+                                Triangle::from_array([a.0, b.0, c.0])
+                            }
+                        };
+                        t.material = material;
+                        triangles.push(t);
+                    }
+                }
+                // comments, texture coordinates, groups, objects, smoothing groups, lines and points
+                _ => {}
+            }
+            Ok(())
+        })();
+        result.map_err(|e: anyhow::Error| anyhow!("line {}: {}", number + 1, e))?;
+    }
+    generate_missing_normals(&mut triangles, &missing_normals);
+    Ok(triangles)
+}
+
+// Lädt eine OBJ-Datei, mtllib-Pfade beziehen sich auf deren Verzeichnis
+pub fn load_obj(path: &Path) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    let obj = fs::read_to_string(path)?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials = Vec::new();
+    let triangles = parse_obj(&obj, &mut materials, &mut |file| {
+        let mtl_path = base.join(file);
+        fs::read_to_string(&mtl_path)
+            .with_context(|| format!("Cannot read material library {}", mtl_path.display()))
+    })?;
+    Ok((materials, triangles))
+}
+#[test]
+fn test_load_obj() {
+    let (materials, triangles) = load_obj(Path::new("scene/wuerfel.obj")).unwrap();
+    assert_eq!(materials.len(), 2);
+    // six quads
+    assert_eq!(triangles.len(), 12);
+    assert_eq!(materials[triangles[0].material].name, "holz");
+    // edges of the cube are creased, so every corner keeps the flat face normal
+    for t in &triangles {
+        for n in t.n {
+            assert!((n - t.normal()).length() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn test_write_obj() {
+    let mut glas = Material::new("blaues glas", Color::new(0.2, 0.4, 0.6, 0.25));
+    glas.brechungsindex = 1.33;
+    // Ka has no alpha
+    glas.ambient.a = 1.0;
+    let materials = vec![Material::new("rot", Color::new(1.0, 0.0, 0.0, 1.0)), glas];
+    let n = [Vec3A::Z, Vec3A::Z, Vec3A::new(0.0, 0.6, 0.8)];
+    let mut a = Triangle::with_normals([Vec3A::ZERO, Vec3A::X, Vec3A::Y], n);
+    let mut b = Triangle::with_normals([Vec3A::X, Vec3A::ONE, Vec3A::Y], n);
+    a.material = 1;
+    b.material = 0;
+    let mesh = Mesh::from_triangles(&[a, b]);
+    let obj = write_obj(&mesh, &materials, "kopie.mtl");
+    // shared vertices are written once
+    assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 4);
+    let mut parsed_materials = Vec::new();
+    let triangles = parse_obj(&obj, &mut parsed_materials, &mut |file| {
+        assert_eq!(file, "kopie.mtl");
+        Ok(write_mtl(&materials))
+    })
+    .unwrap();
+    assert_eq!(parsed_materials, materials);
+    assert_eq!(Mesh::from_triangles(&triangles), mesh);
+}
+
+// Schreibt die Materialien im MTL-Format. Die Stärke der Spiegelung steckt in MTL in Ks, daher spiegelt ein
+// Material mit illum 3 beim erneuten Einlesen mit reflectance() = 1, spiegelnd.a bleibt nur für matte Materialien
+// exakt erhalten.
+pub fn write_mtl(materials: &[Material]) -> String {
+    let mut mtl = String::new();
+    for m in materials {
+        let rgb = |c: Color| format!("{} {} {}", c.r, c.g, c.b);
+        writeln!(mtl, "newmtl {}", m.name).unwrap();
+        writeln!(mtl, "Ka {}", rgb(m.ambient)).unwrap();
+        writeln!(mtl, "Kd {}", rgb(m.diffus)).unwrap();
+        writeln!(mtl, "Ks {}", rgb(m.spiegelnd)).unwrap();
+        writeln!(mtl, "Ns {}", m.glanz).unwrap();
+        writeln!(mtl, "d {}", m.diffus.a).unwrap();
+        writeln!(mtl, "Ni {}", m.brechungsindex).unwrap();
+        writeln!(mtl, "illum {}\n", if m.isSpiegelnd() { 3 } else { 2 }).unwrap();
+    }
+    mtl
+}
+
+// Schreibt das Netz als OBJ mit je einer Normale pro Eckpunkt, mesh.materials sind Indizes in materials
+pub fn write_obj(mesh: &Mesh, materials: &[Material], mtllib: &str) -> String {
+    let mut obj = String::new();
+    writeln!(obj, "# {} Dreiecke\nmtllib {}", mesh.len(), mtllib).unwrap();
+    for p in &mesh.positions {
+        writeln!(obj, "v {} {} {}", p.x, p.y, p.z).unwrap();
+    }
+    for n in &mesh.normals {
+        writeln!(obj, "vn {} {} {}", n.x, n.y, n.z).unwrap();
+    }
+    let mut current: Option<usize> = None;
+    for (face, &material) in mesh.indices.iter().zip(&mesh.materials) {
+        if current != Some(material) {
+            writeln!(obj, "usemtl {}", materials[material].name).unwrap();
+            current = Some(material);
+        }
+        // positions and normals share the vertex index
+        let [a, b, c] = face.map(|i| i + 1);
+        writeln!(obj, "f {0}//{0} {1}//{1} {2}//{2}", a, b, c).unwrap();
+    }
+    obj
+}
+
+// Speichert das Netz unter path und die Materialien daneben in einer MTL-Datei gleichen Namens
+pub fn save_obj(path: &Path, materials: &[Material], mesh: &Mesh) -> Result<(), anyhow::Error> {
+    let mtl_path = path.with_extension("mtl");
+    let mtllib = mtl_path.file_name().unwrap_or_default().to_string_lossy();
+    fs::write(&mtl_path, write_mtl(materials))
+        .with_context(|| format!("Cannot write material library {}", mtl_path.display()))?;
+    fs::write(path, write_obj(mesh, materials, &mtllib))
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
@@ -0,0 +1,282 @@
+// Monte-Carlo-Pfadverfolgung: indirektes Licht durch kosinusgewichtete diffuse Abtastung, direktes Licht durch
+// Schattenstrahlen zu den Lichtquellen (next event estimation), Abbruch langer Pfade per Russischem Roulette
+
+use crate::color::Color;
+use crate::light_source::Light;
+use crate::material::Material;
+use crate::ray::{Intersection, Ray, EPSILON};
+use crate::sampling::{orthonormal_basis, Rng};
+use crate::scene::Scene;
+use crate::whitted::{reflect, refract, sample_lights};
+use glam::f32::Vec3A;
+
+#[cfg(test)]
+fn quad(corner: Vec3A, a: Vec3A, b: Vec3A, material: usize) -> [crate::triangle::Triangle; 2] {
+    use crate::triangle::Triangle;
+    let mut t = [
+        Triangle::new(corner, corner + a, corner + a + b),
+        Triangle::new(corner, corner + a + b, corner + b),
+    ];
+    t[0].material = material;
+    t[1].material = material;
+    t
+}
+
+#[cfg(test)]
+fn mean(scene: &Scene, ray: &Ray, samples: u32) -> Color {
+    let mut rng = Rng::new(1);
+    let mut sum = Color::black();
+    for _ in 0..samples {
+        sum = sum + trace(scene, ray, 5, &mut rng);
+    }
+    sum * (1.0 / samples as f32)
+}
+
+#[test]
+fn test_cosine_sample() {
+    let mut rng = Rng::new(3);
+    let n = Vec3A::new(1.0, 2.0, -0.5).normalize();
+    let mut sum = 0.0;
+    for _ in 0..10000 {
+        let d = cosine_sample(n, &mut rng);
+        assert!((d.length() - 1.0).abs() < 1e-4);
+        assert!(d.dot(n) >= 0.0);
+        sum += d.dot(n);
+    }
+    // E[cos] for the density cos / π
+    assert!((sum / 10000.0 - 2.0 / 3.0).abs() < 0.01);
+}
+
+#[test]
+fn test_direct_light() {
+    use crate::camera::Camera;
+    use crate::light_source::{AreaLight, LightSource, Shape};
+    use std::f32::consts::PI;
+    // a single Lambertian plane under a black sky receives no indirect light, it reflects albedo / π times the
+    // irradiance E
+    let mut glossy = Material::new("grau", Color::new(0.2, 0.4, 0.6, 1.0));
+    // the highlight of a point light is not part of the diffuse reflection
+    glossy.spiegelnd = Color::white();
+    let mut scene = Scene::new(
+        Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::ZERO),
+        vec![glossy],
+        quad(Vec3A::new(-1.0, -1.0, 0.0), 2.0 * Vec3A::X, 2.0 * Vec3A::Y, 0).to_vec(),
+    );
+    let ray = Ray::new(scene.camera.pos, -Vec3A::Z);
+    let assert_reflects = |scene: &Scene, irradiance: f32| {
+        let c = mean(scene, &ray, 16);
+        let expected = 0.6 / PI * irradiance;
+        assert!((c.b - expected).abs() < 0.01 * expected, "{:?} {}", c, expected);
+        assert!((c.r / c.b - 1.0 / 3.0).abs() < 1e-4);
+    };
+    // E = I / d² for a point light 2 above the plane
+    scene.attenuation.konstant = 0.0;
+    scene.attenuation.quadratisch = 1.0;
+    scene.lights.push(Light::Point(LightSource {
+        pos: Vec3A::new(0.0, 0.0, 2.0),
+        color: Color::white(),
+    }));
+    assert_reflects(&scene, 0.25);
+    // E = π L R² / (h² + R²) under a disk of radiance L and radius R at height h
+    scene.lights = vec![Light::Area(AreaLight {
+        shape: Shape::Disk {
+            center: Vec3A::new(0.0, 0.0, 2.0),
+            normal: -Vec3A::Z,
+            radius: 2.0,
+        },
+        color: Color::white(),
+        samples: 256,
+    })];
+    assert_reflects(&scene, PI * 4.0 / 8.0);
+}
+
+#[test]
+fn test_color_bleeding() {
+    use crate::camera::Camera;
+    let materials = vec![
+        Material::new("weiss", Color::new(0.8, 0.8, 0.8, 1.0)),
+        Material::new("rot", Color::new(0.8, 0.0, 0.0, 1.0)),
+    ];
+    // white floor under a white sky, every bounce escapes, so the floor reflects its albedo
+    let floor = quad(Vec3A::new(-1.0, 0.0, 1.0), 2.0 * Vec3A::X, -2.0 * Vec3A::Z, 0);
+    let mut scene = Scene::new(
+        Camera::new(Vec3A::new(0.0, 2.0, 2.0), Vec3A::ZERO),
+        materials,
+        floor.to_vec(),
+    );
+    scene.background = Color::white();
+    let ray = Ray::new(Vec3A::new(0.5, 1.0, 0.0), -Vec3A::Y);
+    let c = mean(&scene, &ray, 100);
+    assert!((c.r - 0.8).abs() < 1e-5 && (c.g - 0.8).abs() < 1e-5);
+    // a red wall beside the floor tints it red
+    for t in quad(Vec3A::new(0.0, 0.0, 1.0), Vec3A::Y, -2.0 * Vec3A::Z, 1) {
+        scene.mesh.push(&t);
+    }
+    scene.build_accelerator(crate::accelerator::AcceleratorKind::BruteForce);
+    let c = mean(&scene, &Ray::new(Vec3A::new(0.1, 1.0, 0.0), -Vec3A::Y), 4000);
+    assert!(c.r > c.g + 0.05, "{:?}", c);
+    assert!(c.g < 0.8);
+}
+
+#[test]
+fn test_mirror_and_glass() {
+    use crate::camera::Camera;
+    let mut mirror = Material::new("spiegel", Color::black());
+    mirror.spiegelnd = Color::new(1.0, 1.0, 1.0, 0.0);
+    let mut glass = Material::new("glas", Color::black());
+    glass.diffus.a = 0.0;
+    for material in [mirror, glass] {
+        let mut scene = Scene::new(
+            Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::ZERO),
+            vec![material],
+            quad(Vec3A::new(-1.0, -1.0, 0.0), 2.0 * Vec3A::X, 2.0 * Vec3A::Y, 0).to_vec(),
+        );
+        scene.background = Color::new(0.0, 0.0, 1.0, 1.0);
+        let c = mean(&scene, &Ray::new(scene.camera.pos, -Vec3A::Z), 10);
+        assert!((c.b - 1.0).abs() < 1e-5, "{:?}", c);
+    }
+}
+
+// Richtung mit Dichte cos θ / π um die Normale n (Malley)
+fn cosine_sample(n: Vec3A, rng: &mut Rng) -> Vec3A {
+    let (t, s) = orthonormal_basis(n);
+    let r = rng.next_f32().sqrt();
+    let phi = 2.0 * std::f32::consts::PI * rng.next_f32();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    (r * phi.cos() * t + r * phi.sin() * s + z * n).normalize()
+}
+
+fn max_component(c: Color) -> f32 {
+    c.r.max(c.g).max(c.b)
+}
+
+// Wählt zufällig eine Fortsetzung des Pfads, mit Wahrscheinlichkeit proportional zu ihrem Gewicht
+fn choose<T: Copy>(events: &[(T, Color)], rng: &mut Rng) -> Option<(T, Color)> {
+    let total: f32 = events.iter().map(|(_, w)| max_component(*w)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut x = rng.next_f32() * total;
+    let mut chosen = None;
+    for &(event, weight) in events {
+        let p = max_component(weight);
+        if p <= 0.0 {
+            continue;
+        }
+        // divided by the probability p / total of this choice
+        chosen = Some((event, weight * (total / p)));
+        if x < p {
+            break;
+        }
+        x -= p;
+    }
+    chosen
+}
+
+#[derive(Clone, Copy)]
+enum Event {
+    Diffuse,
+    Mirror,
+    Transmission,
+}
+
+// Direktes Licht für die Lambert-BRDF diffus / π. Light::incident enthält den Faktor 1 / π bei Flächenlichtquellen
+// bereits, bei den übrigen Lichtquellen wird er hier ergänzt. Der Glanzpunkt nach Phong fehlt, spiegelndes Licht
+// kommt nur über Event::Mirror hinzu, sonst würden leuchtende Dreiecke doppelt gezählt.
+fn next_event(
+    scene: &Scene,
+    material: &Material,
+    p: Vec3A,
+    normal: Vec3A,
+    n: Vec3A,
+    rng: &mut Rng,
+) -> Color {
+    sample_lights(scene, p, normal, n, rng, |light, l, color| {
+        let brdf = match light {
+            Light::Area(_) => material.diffus,
+            _ => material.diffus * std::f32::consts::FRAC_1_PI,
+        };
+        color * brdf * n.dot(l)
+    })
+}
+
+// Erwartungstreue Schätzung der Strahldichte entlang ray. Die Materialien streuen diffus nach Lambert, spiegeln
+// ideal und brechen wie bei Whitted, statt des konstanten ambienten Anteils wird das indirekte Licht verfolgt. Nach
+// depth Streuungen entscheidet Russisches Roulette über den Abbruch.
+pub fn trace(scene: &Scene, ray: &Ray, depth: u32, rng: &mut Rng) -> Color {
+    let mut color = Color::black();
+    // product of the weights along the path
+    let mut throughput = Color::white();
+    let mut ray = *ray;
+    let mut bounce = 0;
+    // emission found by diffuse bounces is already counted by the shadow rays of the previous hit
+    let mut count_emission = true;
+    loop {
+        let hit = match scene.intersect(&ray, scene.camera.clippingFar) {
+            Some(hit) => hit,
+            None => return color + throughput * scene.background,
+        };
+        let triangle = scene.mesh.triangle(hit.triangle);
+        let material: &Material = scene.material(&triangle);
+        let Intersection {
+            p,
+            mut normal,
+            shading_normal: mut n,
+            ..
+        } = Intersection::new(&ray, hit, &triangle);
+        let inside = normal.dot(ray.dir) > 0.0;
+        if inside {
+            normal = -normal;
+            n = -n;
+        }
+        let opacity = 1.0 - material.transparency();
+        let direct = next_event(scene, material, p, normal, n, rng);
+        color = color + throughput * direct * opacity;
+        if count_emission {
+            color = color + throughput * scene.emission(triangle.material);
+        }
+
+        let mirror_weight = if material.isSpiegelnd() {
+            material.spiegelnd * material.reflectance()
+        } else {
+            Color::black()
+        };
+        let transmission = material.transparency();
+        let events = [
+            (Event::Diffuse, material.diffus * opacity),
+            (Event::Mirror, mirror_weight),
+            (Event::Transmission, Color::new(transmission, transmission, transmission, 1.0)),
+        ];
+        let (event, weight) = match choose(&events, rng) {
+            Some(chosen) => chosen,
+            None => return color,
+        };
+        let mirrored = reflect(ray.dir, n);
+        count_emission = !matches!(event, Event::Diffuse);
+        ray = match event {
+            Event::Diffuse => Ray::new(p + EPSILON * normal, cosine_sample(n, rng)),
+            Event::Mirror => Ray::new(p + EPSILON * normal, mirrored),
+            Event::Transmission => {
+                let eta = if inside {
+                    material.brechungsindex
+                } else {
+                    1.0 / material.brechungsindex
+                };
+                match refract(ray.dir, n, eta) {
+                    Some(dir) => Ray::new(p - EPSILON * normal, dir),
+                    None => Ray::new(p + EPSILON * normal, mirrored),
+                }
+            }
+        };
+        // the cosine-weighted direction cancels cos θ and 1 / π of the Lambert BRDF, only the albedo remains
+        throughput = throughput * weight;
+        bounce += 1;
+        if bounce > depth {
+            let survival = max_component(throughput).min(0.95);
+            if rng.next_f32() >= survival {
+                return color;
+            }
+            throughput = throughput * (1.0 / survival);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+// Import und Export von Stanford PLY (ASCII und binär) mit Positionen, Normalen und Eckpunktfarben
+
+use crate::color::Color;
+use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::mesh_processing::generate_missing_normals;
+use crate::triangle::Triangle;
+use anyhow::{anyhow, Context};
+use glam::f32::Vec3A;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[cfg(test)]
+const HEADER: &str = "ply
+format ascii 1.0
+comment zwei Dreiecke als Quadrat, vor end_header
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+#[test]
+fn test_parse_ascii() {
+    let ply = format!(
+        "{}0 0 0 255 0 0\n1 0 0 255 0 0\n1 1 0 255 0 0\n0 1 0 0 0 255\n4 0 1 2 3\n",
+        HEADER
+    );
+    let (materials, triangles) = parse_ply(ply.as_bytes()).unwrap();
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(
+        triangles[1].p,
+        [Vec3A::ZERO, Vec3A::new(1.0, 1.0, 0.0), Vec3A::Y]
+    );
+    assert_eq!(triangles[0].n, [Vec3A::Z; 3]);
+    // the face colors are the averages of the vertex colors
+    assert_eq!(materials.len(), 2);
+    assert_eq!(
+        materials[triangles[0].material].diffus,
+        Color::new(1.0, 0.0, 0.0, 1.0)
+    );
+    let c = materials[triangles[1].material].diffus;
+    assert!((c.r - 2.0 / 3.0).abs() < 0.01 && (c.b - 1.0 / 3.0).abs() < 0.01);
+    for face in ["4 0 1 2 -1", "4 0 1 2 4", "4 0 1 2 2.5"] {
+        let ply = format!(
+            "{}0 0 0 255 0 0\n1 0 0 255 0 0\n1 1 0 255 0 0\n0 1 0 0 0 255\n{}\n",
+            HEADER, face
+        );
+        let err = parse_ply(ply.as_bytes()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("PLY vertex index out of range"),
+            "{}",
+            err
+        );
+    }
+}
+
+#[test]
+fn test_parse_binary() {
+    let header = "ply
+format binary_big_endian 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+element face 1
+property uchar flags
+property list uchar uint vertex_indices
+end_header
+";
+    let mut ply = header.as_bytes().to_vec();
+    for p in [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]] {
+        p.iter().for_each(|x: &f64| ply.extend(x.to_be_bytes()));
+        [0f32, 0.0, -1.0]
+            .iter()
+            .for_each(|x| ply.extend(x.to_be_bytes()));
+    }
+    ply.extend([7, 3]);
+    [0u32, 1, 2]
+        .iter()
+        .for_each(|i| ply.extend(i.to_be_bytes()));
+    let (materials, triangles) = parse_ply(&ply).unwrap();
+    assert_eq!(materials.len(), 1);
+    assert_eq!(triangles.len(), 1);
+    assert_eq!(triangles[0].p[1], Vec3A::new(2.0, 0.0, 0.0));
+    // normals from the file are kept even if they disagree with the winding order
+    assert_eq!(triangles[0].n, [-Vec3A::Z; 3]);
+    ply.pop();
+    assert_eq!(
+        parse_ply(&ply).unwrap_err().to_string(),
+        "Unexpected end of PLY data"
+    );
+    let ascii = HEADER.replace("vertex_indices", "vertex_indices\nelement edge 1");
+    assert!(parse_ply(ascii.as_bytes()).is_err());
+}
+
+// Datentyp einer Eigenschaft
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self, anyhow::Error> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(anyhow!("Unknown PLY property type: {}", name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // factor that maps integer colors to [0, 1]
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    ty: Scalar,
+    // type of the length prefix for list properties
+    count: Option<Scalar>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn index(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|p| p.count.is_none() && names.contains(&p.name.as_str()))
+    }
+}
+
+// Werte des Dateirumpfs in der im Header angegebenen Kodierung
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, ty: Scalar) -> Result<f64, anyhow::Error> {
+        let end = || anyhow!("Unexpected end of PLY data");
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or_else(end)?;
+                token
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid number in PLY data: {}", token))
+            }
+            Body::Binary { data, big_endian } => {
+                let size = ty.size();
+                if data.len() < size {
+                    return Err(end());
+                }
+                let (bytes, rest) = data.split_at(size);
+                *data = rest;
+                // convert to little endian
+                let mut b = [0u8; 8];
+                b[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    b[..size].reverse();
+                }
+                Ok(match ty {
+                    Scalar::I8 => b[0] as i8 as f64,
+                    Scalar::U8 => b[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(b),
+                })
+            }
+        }
+    }
+
+    // Liest alle Eigenschaften eines Elements, Listen werden in lists abgelegt
+    fn read_element(
+        &mut self,
+        element: &Element,
+        values: &mut Vec<f64>,
+        lists: &mut Vec<Vec<f64>>,
+    ) -> Result<(), anyhow::Error> {
+        values.clear();
+        lists.clear();
+        for property in &element.properties {
+            match property.count {
+                None => values.push(self.read(property.ty)?),
+                Some(count) => {
+                    values.push(0.0);
+                    let n = self.read(count)? as usize;
+                    let list = (0..n)
+                        .map(|_| self.read(property.ty))
+                        .collect::<Result<_, _>>()?;
+                    lists.push(list);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Zerlegt den Header, liefert die Elemente, die Kodierung und den Rest der Datei
+fn parse_header(data: &[u8]) -> Result<(Vec<Element>, Body<'_>), anyhow::Error> {
+    const END: &[u8] = b"end_header";
+    if !data.starts_with(b"ply") {
+        return Err(anyhow!("Not a PLY file"));
+    }
+    // only a whole line, end_header may also occur inside a comment
+    let mut end = 0;
+    let body_start = loop {
+        let line_end = data[end..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|i| end + i);
+        if data[end..line_end.unwrap_or(data.len())].trim_ascii() == END {
+            break line_end.map_or(data.len(), |i| i + 1);
+        }
+        end = line_end.ok_or(anyhow!("PLY header without end_header"))? + 1;
+    };
+    let header = std::str::from_utf8(&data[..end])?;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut format = None;
+    for line in header.lines().skip(1) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", f, _version] => format = Some(f.to_string()),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid element count: {}", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, ty, name] => elements
+                .last_mut()
+                .ok_or(anyhow!("PLY property before element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    ty: Scalar::parse(ty)?,
+                    count: Some(Scalar::parse(count)?),
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or(anyhow!("PLY property before element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    ty: Scalar::parse(ty)?,
+                    count: None,
+                }),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(anyhow!("Invalid PLY header line: {}", line)),
+        }
+    }
+    let body = &data[body_start..];
+    let body = match format.as_deref() {
+        Some("ascii") => Body::Ascii(std::str::from_utf8(body)?.split_ascii_whitespace()),
+        Some("binary_little_endian") => Body::Binary {
+            data: body,
+            big_endian: false,
+        },
+        Some("binary_big_endian") => Body::Binary {
+            data: body,
+            big_endian: true,
+        },
+        Some(f) => return Err(anyhow!("Unsupported PLY format: {}", f)),
+        None => return Err(anyhow!("PLY header without format")),
+    };
+    Ok((elements, body))
+}
+
+// Liest Dreiecke aus PLY-Daten, Polygone werden als Fächer trianguliert. Mit Eckpunktfarben erhält jedes Dreieck
+// ein mattes Material in der gemittelten Farbe, sonst Material::default_grey. Fehlende Normalen werden berechnet.
+pub fn parse_ply(data: &[u8]) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    let (elements, mut body) = parse_header(data)?;
+    let mut positions: Vec<Vec3A> = Vec::new();
+    let mut normals: Vec<Vec3A> = Vec::new();
+    let mut colors: Vec<Vec3A> = Vec::new();
+    let mut faces: Vec<Vec<usize>> = Vec::new();
+    let (mut values, mut lists) = (Vec::new(), Vec::new());
+    for element in &elements {
+        let find = |names: [&str; 3]| -> Option<[usize; 3]> {
+            let [a, b, c] = names.map(|n| element.index(&[n, &format!("diffuse_{}", n)]));
+            Some([a?, b?, c?])
+        };
+        let position = find(["x", "y", "z"]);
+        let normal = find(["nx", "ny", "nz"]);
+        let color = find(["red", "green", "blue"]);
+        let color_scale = color.map_or(1.0, |c| element.properties[c[0]].ty.color_scale());
+        let indices = element
+            .properties
+            .iter()
+            .filter(|p| p.count.is_some())
+            .position(|p| p.name == "vertex_indices" || p.name == "vertex_index");
+        for _ in 0..element.count {
+            body.read_element(element, &mut values, &mut lists)?;
+            let vector =
+                |i: [usize; 3], scale: f64| Vec3A::from(i.map(|i| (values[i] * scale) as f32));
+            if element.name == "vertex" {
+                positions.push(vector(
+                    position.ok_or(anyhow!("PLY vertex without x, y and z"))?,
+                    1.0,
+                ));
+                normal.iter().for_each(|&i| normals.push(vector(i, 1.0)));
+                color
+                    .iter()
+                    .for_each(|&i| colors.push(vector(i, color_scale)));
+            } else if element.name == "face" {
+                let list = &lists[indices.ok_or(anyhow!("PLY face without vertex_indices"))?];
+                let face = list
+                    .iter()
+                    .map(|&i| match i >= 0.0 && i.fract() == 0.0 {
+                        true => Ok(i as usize),
+                        false => Err(anyhow!("PLY vertex index out of range: {}", i)),
+                    })
+                    .collect::<Result<_, _>>()?;
+                faces.push(face);
+            }
+        }
+    }
+    let mut materials: Vec<Material> = Vec::new();
+    // materials for vertex colors, keyed by the 8 bit color
+    let mut material_ids: HashMap<[u8; 3], usize> = HashMap::new();
+    let mut triangles: Vec<Triangle> = Vec::new();
+    for face in faces {
+        if let Some(&i) = face.iter().find(|&&i| i >= positions.len()) {
+            return Err(anyhow!("PLY vertex index out of range: {}", i));
+        }
+        for k in 1..face.len().saturating_sub(1) {
+            let v = [face[0], face[k], face[k + 1]];
+            let p = v.map(|i| positions[i]);
+            let mut t = if normals.is_empty() {
+                Triangle::from_array(p)
+            } else {
+                Triangle::with_normals(p, v.map(|i| normals[i]))
+            };
+            let color = if colors.is_empty() {
+                Vec3A::splat(0.8)
+            } else {
+                (colors[v[0]] + colors[v[1]] + colors[v[2]]) / 3.0
+            };
+            let key = color
+                .to_array()
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            t.material = *material_ids.entry(key).or_insert_with(|| {
+                materials.push(if colors.is_empty() {
+                    Material::default_grey()
+                } else {
+                    let name = format!("ply_{:02x}{:02x}{:02x}", key[0], key[1], key[2]);
+                    let [r, g, b] = key.map(|c| c as f32 / 255.0);
+                    Material::new(&name, Color::new(r, g, b, 1.0))
+                });
+                materials.len() - 1
+            });
+            triangles.push(t);
+        }
+    }
+    if normals.is_empty() {
+        let all: Vec<usize> = (0..triangles.len()).collect();
+        generate_missing_normals(&mut triangles, &all);
+    }
+    Ok((materials, triangles))
+}
+
+pub fn load_ply(path: &Path) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    parse_ply(&fs::read(path)?)
+}
+
+#[test]
+fn test_write_ply() {
+    let materials = vec![
+        Material::new("rot", Color::new(1.0, 0.0, 0.0, 1.0)),
+        Material::new("blau", Color::new(0.0, 0.0, 1.0, 1.0)),
+    ];
+    let mut a = Triangle::from_array([Vec3A::ZERO, Vec3A::X, Vec3A::Y]);
+    let mut b = Triangle::from_array([Vec3A::X, Vec3A::ONE, Vec3A::Y]);
+    a.material = 0;
+    b.material = 1;
+    let mesh = Mesh::from_triangles(&[a.clone(), b.clone()]);
+    let ply = write_ply(&mesh, &materials);
+    // the shared edge is duplicated because its vertices get a different color per material
+    assert!(ply.starts_with(b"ply\nformat binary_little_endian 1.0\nelement vertex 6\n"));
+    let (parsed_materials, triangles) = parse_ply(&ply).unwrap();
+    assert_eq!(parsed_materials.len(), 2);
+    for (original, t) in [a, b].iter().zip(&triangles) {
+        assert_eq!((t.p, t.n), (original.p, original.n));
+        assert_eq!(
+            parsed_materials[t.material].diffus,
+            materials[original.material].diffus
+        );
+    }
+}
+
+// Schreibt das Netz als binäres PLY. PLY kennt nur Eckpunktfarben, daher erhält jeder Eckpunkt die diffuse Farbe
+// seines Materials und wird für jedes angrenzende Material einmal geschrieben.
+pub fn write_ply(mesh: &Mesh, materials: &[Material]) -> Vec<u8> {
+    let mut vertex_ids: HashMap<(u32, usize), u32> = HashMap::new();
+    let mut vertices: Vec<(u32, usize)> = Vec::new();
+    let faces: Vec<[u32; 3]> = mesh
+        .indices
+        .iter()
+        .zip(&mesh.materials)
+        .map(|(face, &material)| {
+            face.map(|v| {
+                *vertex_ids.entry((v, material)).or_insert_with(|| {
+                    vertices.push((v, material));
+                    (vertices.len() - 1) as u32
+                })
+            })
+        })
+        .collect();
+    let mut ply = format!(
+        "ply
+format binary_little_endian 1.0
+element vertex {}
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element face {}
+property list uchar uint vertex_indices
+end_header
+",
+        vertices.len(),
+        faces.len()
+    )
+    .into_bytes();
+    for (v, material) in vertices {
+        let (p, n) = (mesh.positions[v as usize], mesh.normals[v as usize]);
+        for x in p.to_array().into_iter().chain(n.to_array()) {
+            ply.extend(x.to_le_bytes());
+        }
+        let c = materials[material].diffus;
+        ply.extend([c.r, c.g, c.b].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+    }
+    for face in faces {
+        ply.push(3);
+        face.iter().for_each(|i| ply.extend(i.to_le_bytes()));
+    }
+    ply
+}
+
+pub fn save_ply(path: &Path, materials: &[Material], mesh: &Mesh) -> Result<(), anyhow::Error> {
+    fs::write(path, write_ply(mesh, materials))
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
@@ -0,0 +1,164 @@
+// Interaktive OpenGL-Vorschau der Dreiecke einer Szene
+// https://raw.githubusercontent.com/rust-tutorials/learn-opengl/main/examples/002-triangle-arrays2.rs
+
+use crate::learn::*;
+use crate::null_str;
+use crate::scene::Scene;
+use beryllium::*;
+use core::{
+    convert::{TryFrom, TryInto},
+    mem::{size_of, size_of_val},
+};
+use glam::f32::Mat4;
+use ogl33::*;
+use std::collections::HashMap;
+
+const WINDOW_TITLE: &str = "Raytracer";
+
+// position and diffuse material color
+type Vertex = [f32; 6];
+
+//const VERTICES: [Vertex; 3] = [[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.0, 0.5, 0.0]];
+
+const VERT_SHADER: &str = r#"#version 330 core
+  uniform mat4 transform;
+
+  layout (location = 0) in vec3 pos;
+  layout (location = 1) in vec3 color;
+
+  out vec3 vertex_color;
+
+  void main() {
+    gl_Position = transform * vec4(pos,1.0);
+    vertex_color = color;
+  }
+"#;
+
+const FRAG_SHADER: &str = r#"#version 330 core
+  in vec3 vertex_color;
+
+  out vec4 final_color;
+
+  void main() {
+    final_color = vec4(vertex_color, 1.0);
+  }
+"#;
+
+pub fn show(scene: &Scene) {
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::with_capacity(3 * scene.mesh.len());
+    // mesh vertices are only shared between faces with the same material, as the color is a vertex attribute
+    let mut vertex_ids: HashMap<(u32, usize), u32> = HashMap::new();
+    let mesh = &scene.mesh;
+    for (face, &material) in mesh.indices.iter().zip(&mesh.materials) {
+        let c = &scene.materials[material].diffus;
+        for &i in face {
+            let id = *vertex_ids.entry((i, material)).or_insert_with(|| {
+                let p = mesh.positions[i as usize];
+                vertices.push([p.x, -p.y, p.z, c.r, c.g, c.b]);
+                (vertices.len() - 1) as u32
+            });
+            indices.push(id);
+        }
+    }
+    //vertices.extend([-0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.0, 0.5, 0.0].iter().cloned()); // test
+
+    let sdl = SDL::init(InitFlags::Everything).expect("couldn't start SDL");
+    sdl.gl_set_attribute(SdlGlAttr::MajorVersion, 3).unwrap();
+    sdl.gl_set_attribute(SdlGlAttr::MinorVersion, 3).unwrap();
+    sdl.gl_set_attribute(SdlGlAttr::Profile, GlProfile::Core)
+        .unwrap();
+    #[cfg(target_os = "macos")]
+    {
+        sdl.gl_set_attribute(SdlGlAttr::Flags, ContextFlag::ForwardCompatible)
+            .unwrap();
+    }
+
+    let win = sdl
+        .create_gl_window(
+            WINDOW_TITLE,
+            WindowPosition::Centered,
+            800,
+            600,
+            WindowFlags::Shown,
+        )
+        .expect("couldn't make a window and context");
+    win.set_swap_interval(SwapInterval::Vsync);
+
+    unsafe {
+        load_gl_with(|f_name| win.get_proc_address(f_name));
+    }
+
+    clear_color(0.2, 0.3, 0.3, 1.0);
+
+    let vao = VertexArray::new().expect("Couldn't make a VAO");
+    vao.bind();
+
+    let vbo = Buffer::new().expect("Couldn't make a VBO");
+    vbo.bind(BufferType::Array);
+
+    buffer_data(
+        BufferType::Array,
+        bytemuck::cast_slice(&vertices),
+        GL_STATIC_DRAW,
+    );
+
+    let ebo = Buffer::new().expect("Couldn't make the element buffer");
+    ebo.bind(BufferType::ElementArray);
+    buffer_data(
+        BufferType::ElementArray,
+        bytemuck::cast_slice(&indices),
+        GL_STATIC_DRAW,
+    );
+
+    unsafe {
+        glVertexAttribPointer(
+            0,
+            3,
+            GL_FLOAT,
+            GL_FALSE,
+            size_of::<Vertex>().try_into().unwrap(),
+            0 as *const _,
+        );
+        glEnableVertexAttribArray(0);
+        glVertexAttribPointer(
+            1,
+            3,
+            GL_FLOAT,
+            GL_FALSE,
+            size_of::<Vertex>().try_into().unwrap(),
+            size_of::<[f32; 3]>() as *const _,
+        );
+        glEnableVertexAttribArray(1);
+    }
+
+    let shader_program = ShaderProgram::from_vert_frag(VERT_SHADER, FRAG_SHADER).unwrap();
+    shader_program.use_program();
+
+    'main_loop: loop {
+        // handle events this frame
+        while let Some(event) = sdl.poll_events().and_then(Result::ok) {
+            match event {
+                Event::Quit(_) => break 'main_loop,
+                _ => (),
+            }
+        }
+        //let time = sdl.get_ticks() as f32 / 2000.0_f32;
+        //let transform = Mat4::from_rotation_z(time);
+        let transform = Mat4::perspective_lh(1.0,16.0/9.0,scene.camera.clippingNear,scene.camera.clippingFar);
+        // and then draw!
+        unsafe {
+            glClear(GL_COLOR_BUFFER_BIT);
+            let transform_name = null_str!("transform").as_ptr().cast();
+            let transform_loc = glGetUniformLocation(shader_program.0, transform_name);
+            glUniformMatrix4fv(transform_loc, 1, GL_FALSE, &transform.to_cols_array()[0]);
+            glDrawElements(
+                GL_TRIANGLES,
+                indices.len().try_into().unwrap(),
+                GL_UNSIGNED_INT,
+                0 as *const _,
+            );
+        }
+        win.swap_window();
+    }
+}
@@ -0,0 +1,91 @@
+use crate::triangle::Triangle;
+use glam::f32::Vec3A;
+
+// Schnittpunkte näher als EPSILON am Ursprung werden ignoriert, damit Strahlen nicht die eigene Oberfläche treffen
+pub const EPSILON: f32 = 1e-4;
+
+#[test]
+fn test_at() {
+    let r = Ray::new(Vec3A::new(1.0, 0.0, 0.0), Vec3A::new(0.0, 0.0, 2.0));
+    assert_eq!(r.dir, Vec3A::new(0.0, 0.0, 1.0));
+    assert_eq!(r.at(3.0), Vec3A::new(1.0, 0.0, 3.0));
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3A,
+    // always normalized, so that the hit distance t is a length in scene units
+    pub dir: Vec3A,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3A, dir: Vec3A) -> Self {
+        Self {
+            origin,
+            dir: dir.normalize(),
+        }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3A {
+        self.origin + t * self.dir
+    }
+}
+
+// Schnittereignis eines Strahls mit einem Dreieck der Szene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    // distance from the ray origin
+    pub t: f32,
+    // barycentric coordinates with respect to p[1] and p[2], the weight of p[0] is 1-u-v
+    pub u: f32,
+    pub v: f32,
+    // face index in Scene::mesh
+    pub triangle: usize,
+}
+
+#[test]
+fn test_intersection() {
+    let t = Triangle::with_normals(
+        [Vec3A::ZERO, Vec3A::X, Vec3A::Y],
+        [Vec3A::new(1.0, 0.0, -1.0); 3],
+    );
+    let ray = Ray::new(Vec3A::new(0.2, 0.2, 1.0), -Vec3A::Z);
+    let (t_hit, u, v) = t.intersect(&ray).unwrap();
+    let hit = Hit {
+        t: t_hit,
+        u,
+        v,
+        triangle: 0,
+    };
+    let i = Intersection::new(&ray, hit, &t);
+    assert!((i.p - Vec3A::new(0.2, 0.2, 0.0)).length() < 1e-6);
+    assert_eq!(i.normal, Vec3A::Z);
+    // the vertex normals point away from the face normal, so they are flipped to its side
+    assert!((i.shading_normal - Vec3A::new(-1.0, 0.0, 1.0).normalize()).length() < 1e-6);
+}
+
+// Schnittpunkt mit den für die Beleuchtung nötigen Oberflächeninformationen
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection {
+    pub p: Vec3A,
+    // normalized face normal, used for offsetting secondary rays and deciding inside from outside
+    pub normal: Vec3A,
+    // normalized interpolated vertex normal on the same side as normal, used for lighting
+    pub shading_normal: Vec3A,
+}
+
+impl Intersection {
+    pub fn new(ray: &Ray, hit: Hit, triangle: &Triangle) -> Self {
+        let normal = triangle.normal();
+        let mut shading_normal = triangle.shading_normal(hit.u, hit.v);
+        // vertex normals of imported meshes do not always agree with the winding order
+        if shading_normal.dot(normal) < 0.0 {
+            shading_normal = -shading_normal;
+        }
+        Self {
+            p: ray.at(hit.t),
+            normal,
+            shading_normal,
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::color::Color;
+use crate::image::Image;
+use crate::path_tracer;
+use crate::sampling::{Filter, Pattern, Rng, Sampler};
+use crate::scene::Scene;
+use crate::whitted;
+use anyhow::anyhow;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+// Kantenlänge der Kacheln in Pixeln
+const TILE_SIZE: u32 = 16;
+
+#[test]
+fn test_render() {
+    use crate::camera::Camera;
+    use crate::material::Material;
+    use crate::triangle::Triangle;
+    use glam::f32::Vec3A;
+    let mut scene = Scene::new(
+        Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::ZERO),
+        vec![Material::new("orange", Color::new(1.0, 0.5, 0.2, 1.0))],
+        vec![Triangle::new(
+            Vec3A::new(-1.0, -1.0, 0.0),
+            Vec3A::new(1.0, -1.0, 0.0),
+            Vec3A::new(0.0, 1.0, 0.0),
+        )],
+    );
+    scene.background = Color::new(0.2, 0.2, 0.5, 1.0);
+    let image = render(&scene, &Settings::new(32, 24), &mut |_, _| {});
+    assert_eq!(image.pixels.len(), 32 * 24);
+    assert_eq!(image.get(0, 0), scene.background);
+    let center = image.get(16, 12);
+    assert!(center.r > 0.99 && (center.g - 0.5).abs() < 0.01);
+    // more threads and samples must not change flat regions of the image
+    let settings = Settings {
+        samples: 4,
+        threads: 3,
+        ..Settings::new(32, 24)
+    };
+    let supersampled = render(&scene, &settings, &mut |_, _| {});
+    assert_eq!(supersampled.get(0, 0), scene.background);
+    assert_eq!(supersampled.get(31, 23), scene.background);
+}
+
+#[test]
+fn test_tiles() {
+    let t = tiles(40, 20);
+    assert_eq!(t.len(), 6);
+    assert_eq!(
+        t[2],
+        Tile {
+            x: 32,
+            y: 0,
+            width: 8,
+            height: 16
+        }
+    );
+    assert_eq!(t[5].height, 4);
+    let covered: u32 = t.iter().map(|t| t.width * t.height).sum();
+    assert_eq!(covered, 40 * 20);
+}
+
+#[test]
+fn test_deterministic() {
+    use crate::accelerator::AcceleratorKind;
+    use crate::camera::Camera;
+    use crate::material::Material;
+    use glam::f32::Vec3A;
+    let mut scene = Scene::new(
+        Camera::new(Vec3A::new(0.0, 0.0, 3.0), Vec3A::ZERO),
+        vec![Material::new("rot", Color::new(1.0, 0.0, 0.0, 1.0))],
+        crate::bounding_box::random_triangles(100),
+    );
+    scene.build_accelerator(AcceleratorKind::Bvh);
+    let render_with = |threads, integrator| {
+        let mut calls = Vec::new();
+        let settings = Settings {
+            threads,
+            integrator,
+            samples: 2,
+            ..Settings::new(50, 37)
+        };
+        let image = render(&scene, &settings, &mut |done, total| {
+            calls.push((done, total))
+        });
+        assert_eq!(calls, (1..=12).map(|d| (d, 12)).collect::<Vec<_>>());
+        image.pixels
+    };
+    for integrator in [Integrator::Whitted, Integrator::Path] {
+        assert_eq!(render_with(1, integrator), render_with(4, integrator));
+    }
+}
+
+// white half plane on the right side of the image, its slightly slanted edge runs through the middle of pixel column 8
+#[cfg(test)]
+fn edge_scene() -> Scene {
+    use crate::camera::Camera;
+    use crate::material::Material;
+    use crate::triangle::Triangle;
+    use glam::f32::Vec3A;
+    Scene::new(
+        Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::ZERO),
+        vec![Material::new("weiss", Color::new(1.0, 1.0, 1.0, 1.0))],
+        vec![Triangle::new(
+            Vec3A::new(-100.0, -100.0, 0.0),
+            Vec3A::new(-1.0, -100.0, 0.0),
+            Vec3A::new(1.0, 100.0, 0.0),
+        )],
+    )
+}
+
+#[test]
+fn test_antialiasing() {
+    let scene = edge_scene();
+    for pattern in [Pattern::Stratified, Pattern::Halton, Pattern::Sobol] {
+        for filter in [Filter::Box, Filter::Tent, Filter::Gaussian, Filter::Mitchell] {
+            let settings = Settings {
+                samples: 64,
+                pattern,
+                filter,
+                seed: 42,
+                ..Settings::new(17, 5)
+            };
+            let image = render(&scene, &settings, &mut |_, _| {});
+            let edge = image.get(8, 2).r;
+            assert!(edge > 0.1 && edge < 0.9, "{:?} {:?} {}", pattern, filter, edge);
+            assert!(image.get(1, 2).r.abs() < 1e-4 && (image.get(15, 2).r - 1.0).abs() < 1e-4);
+            assert_eq!(render(&scene, &settings, &mut |_, _| {}).pixels, image.pixels);
+            let reseeded = Settings { seed: 43, ..settings };
+            assert_ne!(render(&scene, &reseeded, &mut |_, _| {}).pixels, image.pixels);
+        }
+    }
+}
+
+#[test]
+fn test_adaptive() {
+    let scene = edge_scene();
+    let settings = Settings {
+        samples: 4,
+        threshold: Some(0.01),
+        max_samples: 64,
+        threads: 2,
+        ..Settings::new(17, 5)
+    };
+    let (image, samples) = render_with_samples(&scene, &settings, &mut |_, _| {});
+    // flat regions stop after the first batch, the edge uses the whole budget
+    assert_eq!(samples[2 * 17 + 1], 4);
+    assert_eq!(samples[2 * 17 + 15], 4);
+    assert_eq!(samples[2 * 17 + 8], 64);
+    let edge = image.get(8, 2).r;
+    assert!(edge > 0.1 && edge < 0.9);
+    let fixed = Settings {
+        threshold: None,
+        ..settings
+    };
+    let (_, samples) = render_with_samples(&scene, &fixed, &mut |_, _| {});
+    assert!(samples.iter().all(|&n| n == 4));
+    let map = sample_image(&[0, 32, 64], 3, 1, 64);
+    assert_eq!(map.get(1, 0), Color::new(0.5, 0.5, 0.5, 1.0));
+    assert_eq!(map.get(2, 0), Color::white());
+}
+
+// Verfahren zur Berechnung der Farbe eines Primärstrahls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    // Phong with a constant ambient term, perfect reflection and refraction
+    Whitted,
+    // Monte Carlo path tracing with indirect light
+    Path,
+}
+
+impl FromStr for Integrator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whitted" => Ok(Self::Whitted),
+            "path" => Ok(Self::Path),
+            _ => Err(anyhow!("Unknown integrator: {} (expected whitted or path)", s)),
+        }
+    }
+}
+
+pub struct Settings {
+    // Bildgröße in Pixeln
+    pub width: u32,
+    pub height: u32,
+    pub integrator: Integrator,
+    // maximale Rekursionstiefe für Spiegelung und Brechung, beim Pfadverfolgen die Anzahl der Streuungen vor dem
+    // Russischen Roulette
+    pub max_depth: u32,
+    // Strahlen pro Pixel, bei adaptiver Abtastung pro Durchgang
+    pub samples: u32,
+    pub pattern: Pattern,
+    pub filter: Filter,
+    // Startwert der Zufallszahlen, gleiche Werte ergeben unabhängig von threads das gleiche Bild
+    pub seed: u64,
+    // adaptive sampling: pixels get batches of samples until the standard error of their luminance is below the
+    // threshold or max_samples is reached
+    pub threshold: Option<f32>,
+    pub max_samples: u32,
+    pub threads: usize,
+}
+
+impl Settings {
+    #[cfg(test)]
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            integrator: Integrator::Whitted,
+            max_depth: 5,
+            samples: 1,
+            pattern: Pattern::Stratified,
+            filter: Filter::Box,
+            seed: 0,
+            threshold: None,
+            max_samples: 64,
+            threads: 1,
+        }
+    }
+}
+
+// Gewichtetes Mittel der Abtastungen im Träger des Filters um die Pixelmitte und deren Anzahl. Die Zufallszahlen
+// hängen nur von settings.seed und der Pixelposition ab, so dass das Bild reproduzierbar ist.
+fn render_pixel(scene: &Scene, settings: &Settings, x: u32, y: u32) -> (Color, u32) {
+    let trace = |dx: f32, dy: f32, rng: &mut Rng| {
+        let ray = scene.camera.primary_ray(
+            x as f32 + 0.5 + dx,
+            y as f32 + 0.5 + dy,
+            settings.width,
+            settings.height,
+        );
+        match settings.integrator {
+            Integrator::Whitted => whitted::trace(scene, &ray, settings.max_depth, rng),
+            Integrator::Path => path_tracer::trace(scene, &ray, settings.max_depth, rng),
+        }
+    };
+    let mut rng = Rng::for_pixel(settings.seed, x, y);
+    if settings.samples == 1 && settings.threshold.is_none() {
+        return (trace(0.0, 0.0, &mut rng), 1);
+    }
+    // adaptive sampling needs at least two samples per batch to estimate the variance
+    let (batch, budget) = match settings.threshold {
+        Some(_) => {
+            let batch = settings.samples.max(2);
+            (batch, settings.max_samples.max(batch))
+        }
+        None => (settings.samples, settings.samples),
+    };
+    let mut sampler = Sampler::new(settings.pattern, batch, &mut rng);
+    let radius = settings.filter.radius();
+    let (mut sum, mut weights) = (Color::black(), 0.0);
+    let mut unweighted = Color::black();
+    // Welford's online mean and sum of squared deviations of the luminance
+    let (mut n, mut mean, mut m2) = (0, 0.0, 0.0);
+    loop {
+        for _ in 0..batch.min(budget - n) {
+            let (u, v) = sampler.sample(n, &mut rng);
+            let (dx, dy) = ((2.0 * u - 1.0) * radius, (2.0 * v - 1.0) * radius);
+            let weight = settings.filter.weight(dx, dy);
+            let c = trace(dx, dy, &mut rng);
+            sum = sum + c * weight;
+            unweighted = unweighted + c;
+            weights += weight;
+            n += 1;
+            let delta = c.luminance() - mean;
+            mean += delta / n as f32;
+            m2 += delta * (c.luminance() - mean);
+        }
+        // standard error of the mean luminance
+        let error = (m2 / ((n - 1) as f32 * n as f32)).sqrt();
+        match settings.threshold {
+            Some(threshold) if n < budget && error > threshold => continue,
+            _ => break,
+        }
+    }
+    // the negative lobes of the Mitchell filter can cancel out the weights of few samples
+    if weights.abs() < 1e-3 {
+        return (unweighted * (1.0 / n as f32), n);
+    }
+    (sum * (1.0 / weights), n)
+}
+
+// Rechteckiger Bildausschnitt, der als Ganzes von einem Thread gerendert wird
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// Zerlegt das Bild zeilenweise in Kacheln der Größe TILE_SIZE, am rechten und unteren Rand auch kleiner
+fn tiles(width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(TILE_SIZE as usize) {
+        for x in (0..width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+fn render_tile(scene: &Scene, settings: &Settings, tile: &Tile) -> Vec<(Color, u32)> {
+    let mut pixels = Vec::with_capacity((tile.width * tile.height) as usize);
+    for y in tile.y..tile.y + tile.height {
+        for x in tile.x..tile.x + tile.width {
+            pixels.push(render_pixel(scene, settings, x, y));
+        }
+    }
+    pixels
+}
+
+// Wie render_with_samples, nur das Bild
+#[cfg(test)]
+pub fn render(scene: &Scene, settings: &Settings, progress: &mut dyn FnMut(usize, usize)) -> Image {
+    render_with_samples(scene, settings, progress).0
+}
+
+// Rendert die Szene auf der CPU. settings.threads Threads holen sich nacheinander Kacheln ab, progress wird nach
+// jeder fertigen Kachel mit der Anzahl der fertigen und aller Kacheln aufgerufen. Liefert das Bild und die Anzahl
+// der Abtastungen jedes Pixels zeilenweise.
+pub fn render_with_samples(
+    scene: &Scene,
+    settings: &Settings,
+    progress: &mut dyn FnMut(usize, usize),
+) -> (Image, Vec<u32>) {
+    let mut image = Image::new(settings.width, settings.height);
+    let mut samples = vec![0; (settings.width * settings.height) as usize];
+    let tiles = tiles(settings.width, settings.height);
+    let next_tile = AtomicUsize::new(0);
+    thread::scope(|s| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..settings.threads.max(1) {
+            let sender = sender.clone();
+            let (tiles, next_tile) = (&tiles, &next_tile);
+            s.spawn(move || {
+                while let Some(tile) = tiles.get(next_tile.fetch_add(1, Ordering::Relaxed)) {
+                    let pixels = render_tile(scene, settings, tile);
+                    if sender.send((*tile, pixels)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // the workers hold the remaining senders, so the receiver ends when all tiles are done
+        drop(sender);
+        for (done, (tile, pixels)) in receiver.iter().enumerate() {
+            for (i, (c, n)) in pixels.into_iter().enumerate() {
+                let (x, y) = (tile.x + i as u32 % tile.width, tile.y + i as u32 / tile.width);
+                image.set(x, y, c);
+                samples[(y * settings.width + x) as usize] = n;
+            }
+            progress(done + 1, tiles.len());
+        }
+    });
+    (image, samples)
+}
+
+// Graustufenbild der Abtastungen pro Pixel, weiß entspricht max Abtastungen
+pub fn sample_image(samples: &[u32], width: u32, height: u32, max: u32) -> Image {
+    let mut image = Image::new(width, height);
+    for (pixel, &n) in image.pixels.iter_mut().zip(samples) {
+        let v = n as f32 / max.max(1) as f32;
+        *pixel = Color::new(v, v, v, 1.0);
+    }
+    image
+}
@@ -0,0 +1,356 @@
+// Abtastmuster und Rekonstruktionsfilter für das Supersampling
+
+use anyhow::anyhow;
+use glam::f32::Vec3A;
+use std::str::FromStr;
+
+#[test]
+fn test_patterns() {
+    let mut rng = Rng::for_pixel(7, 3, 4);
+    for pattern in [Pattern::Stratified, Pattern::Halton, Pattern::Sobol] {
+        let mut sampler = Sampler::new(pattern, 16, &mut rng);
+        let points: Vec<(f32, f32)> = (0..16).map(|i| sampler.sample(i, &mut rng)).collect();
+        let mut cells = [0; 16];
+        let mut columns = [0; 16];
+        for (u, v) in points {
+            assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+            cells[(u * 4.0) as usize + 4 * (v * 4.0) as usize] += 1;
+            columns[(u * 16.0) as usize] += 1;
+        }
+        // the shifted Halton points are only stratified along each axis
+        if pattern == Pattern::Halton {
+            assert_eq!(columns, [1; 16]);
+        } else {
+            assert_eq!(cells, [1; 16], "{:?}", pattern);
+        }
+    }
+    // counts that are not squares use distinct cells of a near-square grid, for primes some cells are skipped
+    for (n, grid) in [
+        (2, (2, 1)),
+        (5, (3, 2)),
+        (6, (3, 2)),
+        (7, (3, 3)),
+        (12, (4, 3)),
+        (13, (4, 4)),
+    ] {
+        let mut used = vec![0; (grid.0 * grid.1) as usize];
+        for _ in 0..1000 {
+            let mut sampler = Sampler::new(Pattern::Stratified, n, &mut rng);
+            let (columns, rows) = (sampler.columns, sampler.rows);
+            assert_eq!((columns, rows), grid);
+            let mut cells = vec![0; used.len()];
+            for i in 0..n {
+                let (u, v) = sampler.sample(i, &mut rng);
+                let (column, row) = ((u * columns as f32) as u32, (v * rows as f32) as u32);
+                cells[(column + columns * row) as usize] += 1;
+                used[(column + columns * row) as usize] += 1;
+            }
+            assert_eq!(cells.iter().filter(|&&c| c == 1).count(), n as usize);
+            assert_eq!(cells.iter().sum::<u32>(), n);
+        }
+        // the skipped cells are spread evenly over the pixel
+        let expected = 1000.0 * n as f32 / used.len() as f32;
+        assert!(
+            used.iter()
+                .all(|&c| (c as f32 - expected).abs() < 0.15 * expected),
+            "{} {:?}",
+            n,
+            used
+        );
+    }
+    assert_eq!(radical_inverse(3, 5), 7.0 / 9.0);
+    assert_eq!(sobol(3, [0, 0]), (0.75, 0.25));
+}
+
+#[test]
+fn test_rng() {
+    let first = |x, y| Rng::for_pixel(1, x, y).next_f32();
+    assert_eq!(first(5, 6), first(5, 6));
+    assert_ne!(first(5, 6), first(6, 5));
+    assert_ne!(Rng::for_pixel(2, 5, 6).next_f32(), first(5, 6));
+    let mut rng = Rng::new(0);
+    let mean = (0..10000).map(|_| rng.next_f32()).sum::<f32>() / 10000.0;
+    assert!((mean - 0.5).abs() < 0.01);
+}
+
+#[test]
+fn test_filters() {
+    for filter in [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell,
+    ] {
+        let r = filter.radius();
+        assert!(filter.weight(0.0, 0.0) > 0.0);
+        assert!(filter.weight(0.0, 0.0) >= filter.weight(0.3, -0.2));
+        assert_eq!(filter.weight(r, 0.0), 0.0, "{:?}", filter);
+        assert_eq!(filter.weight(0.1, 0.2), filter.weight(-0.1, 0.2));
+    }
+    assert_eq!(Filter::Tent.weight(0.5, 0.0), 0.5);
+    // the Mitchell-Netravali filter has negative lobes, which sharpen edges
+    assert!(Filter::Mitchell.weight(1.5, 0.0) < 0.0);
+    assert_eq!("gaussian".parse::<Filter>().unwrap(), Filter::Gaussian);
+    assert_eq!("sobol".parse::<Pattern>().unwrap(), Pattern::Sobol);
+    assert!("lanczos".parse::<Filter>().is_err());
+}
+
+// Verteilung der Abtastpunkte innerhalb eines Pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    // one jittered point per cell of a grid
+    Stratified,
+    // Halton sequence in bases 2 and 3, randomly shifted per pixel
+    Halton,
+    // Sobol (0,2) sequence, scrambled per pixel
+    Sobol,
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stratified" => Ok(Self::Stratified),
+            "halton" => Ok(Self::Halton),
+            "sobol" => Ok(Self::Sobol),
+            _ => Err(anyhow!(
+                "Unknown sample pattern: {} (expected stratified, halton or sobol)",
+                s
+            )),
+        }
+    }
+}
+
+// Rekonstruktionsfilter, gewichtet die Abtastungen nach ihrem Abstand zur Pixelmitte
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(Self::Box),
+            "tent" => Ok(Self::Tent),
+            "gaussian" => Ok(Self::Gaussian),
+            "mitchell" => Ok(Self::Mitchell),
+            _ => Err(anyhow!(
+                "Unknown filter: {} (expected box, tent, gaussian or mitchell)",
+                s
+            )),
+        }
+    }
+}
+
+impl Filter {
+    // half width of the support in pixels, the box filter covers exactly one pixel
+    pub fn radius(self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+        }
+    }
+
+    // Gewicht einer Abtastung mit Abstand (dx, dy) von der Pixelmitte, die Filter sind separierbar
+    pub fn weight(self, dx: f32, dy: f32) -> f32 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(self, d: f32) -> f32 {
+        let r = self.radius();
+        let d = d.abs();
+        if d >= r {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - d / r,
+            // shifted down so that it reaches 0 at the radius
+            Filter::Gaussian => {
+                const ALPHA: f32 = 2.0;
+                (-ALPHA * d * d).exp() - (-ALPHA * r * r).exp()
+            }
+            Filter::Mitchell => mitchell(2.0 * d / r),
+        }
+    }
+}
+
+// Mitchell-Netravali filter with B = C = 1/3 on [-2, 2]
+fn mitchell(x: f32) -> f32 {
+    const B: f32 = 1.0 / 3.0;
+    const C: f32 = 1.0 / 3.0;
+    let x = x.abs();
+    let result = if x < 1.0 {
+        (12.0 - 9.0 * B - 6.0 * C) * x.powi(3)
+            + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+            + (6.0 - 2.0 * B)
+    } else {
+        (-B - 6.0 * C) * x.powi(3)
+            + (6.0 * B + 30.0 * C) * x * x
+            + (-12.0 * B - 48.0 * C) * x
+            + (8.0 * B + 24.0 * C)
+    };
+    result / 6.0
+}
+
+// PCG32 (O'Neill), für jedes Pixel neu initialisiert, damit das Bild nicht von der Aufteilung auf Threads abhängt
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const INCREMENT: u64 = 1442695040888963407;
+
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn for_pixel(seed: u64, x: u32, y: u32) -> Self {
+        // splitmix64 finalizer, so that neighbouring pixels get unrelated streams
+        let mut z = seed ^ ((y as u64) << 32 | x as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Self::new(z ^ (z >> 31))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(Self::INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    // gleichverteilt in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        unit(self.next_u32())
+    }
+}
+
+// the upper 24 bits, so that the result is exactly representable and below 1
+fn unit(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn radical_inverse(base: u32, mut i: u32) -> f32 {
+    let mut result = 0.0f64;
+    let mut scale = 1.0 / base as f64;
+    while i > 0 {
+        result += (i % base) as f64 * scale;
+        i /= base;
+        scale /= base as f64;
+    }
+    result as f32
+}
+
+// Die ersten beiden Dimensionen der Sobol-Folge (Kollig und Keller), scramble wird bitweise per XOR angewandt
+fn sobol(i: u32, scramble: [u32; 2]) -> (f32, f32) {
+    let x = i.reverse_bits() ^ scramble[0];
+    let mut y = scramble[1];
+    let mut v = 1u32 << 31;
+    let mut n = i;
+    while n != 0 {
+        if n & 1 != 0 {
+            y ^= v;
+        }
+        n >>= 1;
+        v ^= v >> 1;
+    }
+    (unit(x), unit(y))
+}
+
+// Liefert die Abtastpunkte eines Pixels im Einheitsquadrat, die Zufallswerte kommen aus dem Rng des Pixels
+pub struct Sampler {
+    pattern: Pattern,
+    // grid of the stratified pattern and the cell it starts in
+    columns: u32,
+    rows: u32,
+    start: u32,
+    // Cranley-Patterson shift for Halton, XOR scramble for Sobol
+    offset: (f32, f32),
+    scramble: [u32; 2],
+}
+
+impl Sampler {
+    // n is the number of samples the stratified grid is laid out for, the grid is the smallest near-square one
+    // with at least n cells, the surplus cells are skipped starting at a random cell so every cell is equally likely
+    pub fn new(pattern: Pattern, n: u32, rng: &mut Rng) -> Self {
+        let n = n.max(1);
+        let columns = (1..=n).find(|&c| c * c >= n).unwrap_or(n);
+        let rows = n.div_ceil(columns);
+        let mut start = 0;
+        let (offset, scramble) = match pattern {
+            Pattern::Stratified => {
+                if columns * rows > n {
+                    start = rng.next_u32() % (columns * rows);
+                }
+                ((0.0, 0.0), [0, 0])
+            }
+            Pattern::Halton => ((rng.next_f32(), rng.next_f32()), [0, 0]),
+            Pattern::Sobol => ((0.0, 0.0), [rng.next_u32(), rng.next_u32()]),
+        };
+        Self {
+            pattern,
+            columns,
+            rows,
+            start,
+            offset,
+            scramble,
+        }
+    }
+
+    // i-ter Punkt, beim geschichteten Muster wiederholt sich das Gitter nach columns * rows Punkten
+    pub fn sample(&mut self, i: u32, rng: &mut Rng) -> (f32, f32) {
+        match self.pattern {
+            Pattern::Stratified => {
+                let cell = (self.start + i) % (self.columns * self.rows);
+                let (column, row) = (cell % self.columns, cell / self.columns);
+                (
+                    (column as f32 + rng.next_f32()) / self.columns as f32,
+                    (row as f32 + rng.next_f32()) / self.rows as f32,
+                )
+            }
+            Pattern::Halton => {
+                let shift = |x: f32, offset: f32| {
+                    let x = x + offset;
+                    // stays below 1 even if the sum rounds up
+                    if x >= 1.0 {
+                        (x - 1.0).min(1.0 - f32::EPSILON)
+                    } else {
+                        x
+                    }
+                };
+                (
+                    shift(radical_inverse(2, i), self.offset.0),
+                    shift(radical_inverse(3, i), self.offset.1),
+                )
+            }
+            Pattern::Sobol => sobol(i, self.scramble),
+        }
+    }
+}
+
+// Zwei zu n und zueinander senkrechte Einheitsvektoren (Duff et al.), n muss normiert sein
+pub fn orthonormal_basis(n: Vec3A) -> (Vec3A, Vec3A) {
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        Vec3A::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        Vec3A::new(b, sign + n.y * n.y * a, -n.y),
+    )
+}
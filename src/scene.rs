@@ -1,37 +1,120 @@
 use crate::color::Color;
 use crate::material::Material;
+use crate::mesh::Mesh;
 use crate::triangle::Triangle;
+use crate::accelerator::{self, Accelerator, AcceleratorKind};
 use crate::camera::Camera;
-use std::collections::HashMap;
+use crate::light_source::{AreaLight, Attenuation, Light, Shape};
+use crate::ray::{Hit, Ray};
+use glam::f32::Vec3A;
 
+#[test]
+fn test_intersect_nearest() {
+    let at_z = |z| {
+        Triangle::new(
+            Vec3A::new(-1.0, -1.0, z),
+            Vec3A::new(1.0, -1.0, z),
+            Vec3A::new(0.0, 1.0, z),
+        )
+    };
+    let scene = Scene::new(
+        Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::ZERO),
+        vec![Material::new("weiss", Color::white())],
+        vec![at_z(-1.0), at_z(1.0), at_z(0.0)],
+    );
+    let ray = Ray::new(scene.camera.pos, scene.camera.getSichtVektor());
+    let hit = scene.intersect(&ray, f32::INFINITY).unwrap();
+    assert_eq!(hit.triangle, 1);
+    assert_eq!(hit.t, 4.0);
+    assert!(scene.intersect(&ray, 3.0).is_none());
+}
+
+#[derive(Debug)]
 pub struct Scene {
     /*
     fnTriangulation: String;
     fnSzene: String;
-    bildBreite: i16;
-    bildHoehe: i16;
 
     //double r,g,b;
     */
+    // Bildgröße in Pixeln aus <fenster>
+    pub width: u32,
+    pub height: u32,
     pub background: Color,
     pub ambient: Color,
-    /*
-    double abschwaechung_konstant;
-    double abschwaechung_linear;
-    double abschwaechung_quadratisch;
-
-    int unterteilung; // Unterteilung für das Raumteilungsverfahren
-    */
+    pub attenuation: Attenuation,
+    // Unterteilung für das Raumteilungsverfahren
+    pub subdivision: u32,
     pub camera: Camera,
-    //boundingBox: BoundingBox,
-    pub materials: HashMap<String, Material>,
-    pub triangles: Vec<Triangle>,
-    /*list<Dreieck*> dreiecke,
-    list<Lichtquelle*> lichtquellen,
-    */
+    // Beschleunigungsstruktur über mesh, muss nach Änderungen an mesh mit build_accelerator neu aufgebaut werden
+    pub accelerator: Box<dyn Accelerator>,
+    pub materials: Vec<Material>,
+    pub mesh: Mesh,
+    pub lights: Vec<Light>,
+}
+
+// the accelerator is derived from the other fields and not compared
+impl PartialEq for Scene {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.background == other.background
+            && self.ambient == other.ambient
+            && self.attenuation == other.attenuation
+            && self.subdivision == other.subdivision
+            && self.camera == other.camera
+            && self.materials == other.materials
+            && self.mesh == other.mesh
+            && self.lights == other.lights
+    }
 }
 
 impl Scene {
+    // Szene mit 800x600 Pixeln ohne Lichtquellen mit schwarzem Hintergrund und voller ambienter Helligkeit
+    pub fn new(camera: Camera, materials: Vec<Material>, triangles: Vec<Triangle>) -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            background: Color::black(),
+            ambient: Color::white(),
+            attenuation: Attenuation::none(),
+            subdivision: 0,
+            camera,
+            accelerator: Box::new(accelerator::BruteForce),
+            materials,
+            mesh: Mesh::from_triangles(&triangles),
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn material(&self, triangle: &Triangle) -> &Material {
+        &self.materials[triangle.material]
+    }
+
+    // Baut die Beschleunigungsstruktur neu auf, die Raumteilung verwendet die Tiefe subdivision
+    pub fn build_accelerator(&mut self, kind: AcceleratorKind) {
+        self.accelerator = accelerator::build(kind, &self.mesh, self.subdivision);
+    }
+
+    // Leuchtdichte der Dreiecke eines Materials, das als Flächenlichtquelle dient
+    pub fn emission(&self, material: usize) -> Color {
+        self.lights
+            .iter()
+            .filter_map(|l| match l {
+                Light::Area(AreaLight {
+                    shape: Shape::Emissive { material: m, .. },
+                    color,
+                    ..
+                }) if *m == material => Some(*color),
+                _ => None,
+            })
+            .fold(Color::black(), |sum, c| sum + c)
+    }
+
+    // Sucht den nächsten Schnittpunkt mit Abstand in (EPSILON, t_max)
+    pub fn intersect(&self, ray: &Ray, t_max: f32) -> Option<Hit> {
+        self.accelerator.intersect(&self.mesh, ray, t_max)
+    }
     /*
     pub fn load(filename: &str) -> Self {
         Self {}
@@ -0,0 +1,196 @@
+// Fehler beim Laden von Szenen und Triangulierungen, damit Werkzeuge gezielt auf einzelne Ursachen reagieren können
+
+use crate::dtd::ValidationError;
+use roxmltree::TextPos;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_display() {
+    let pos = TextPos::new(3, 7);
+    let error = SceneError::BadNumber {
+        path: PathBuf::new(),
+        pos,
+        attribute: "x".to_string(),
+        value: "eins".to_string(),
+    };
+    assert_eq!(
+        error.to_string(),
+        "line 3, column 7: invalid number eins in attribute x"
+    );
+    let error = error.in_file(Path::new("szene.xml"));
+    assert_eq!(
+        error.to_string(),
+        "szene.xml: line 3, column 7: invalid number eins in attribute x"
+    );
+    assert_eq!(error.path(), Path::new("szene.xml"));
+    // errors of nested files keep their path
+    let error = error.in_file(Path::new("andere.xml"));
+    assert_eq!(error.path(), Path::new("szene.xml"));
+    let error = SceneError::Import {
+        path: PathBuf::from("nix.obj"),
+        source: anyhow::Error::new(io::Error::new(io::ErrorKind::NotFound, "not found"))
+            .context("OBJ without faces"),
+    };
+    assert_eq!(error.to_string(), "nix.obj: cannot import triangulation");
+    assert_eq!(
+        format!("{:#}", anyhow::Error::new(error)),
+        "nix.obj: cannot import triangulation: OBJ without faces: not found"
+    );
+}
+
+// path is the file the error occurred in, empty while parsing a string that does not belong to a file
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    // not well-formed XML
+    Xml {
+        path: PathBuf,
+        error: ValidationError,
+    },
+    // well-formed, but violates szene.dtd or triangulation.dtd
+    Invalid {
+        path: PathBuf,
+        errors: Vec<ValidationError>,
+    },
+    MissingElement {
+        path: PathBuf,
+        pos: TextPos,
+        parent: String,
+        name: String,
+    },
+    MissingAttribute {
+        path: PathBuf,
+        pos: TextPos,
+        element: String,
+        name: String,
+    },
+    BadNumber {
+        path: PathBuf,
+        pos: TextPos,
+        attribute: String,
+        value: String,
+    },
+    UnknownMaterial {
+        path: PathBuf,
+        pos: TextPos,
+        name: String,
+    },
+    DuplicateMaterial {
+        path: PathBuf,
+        pos: TextPos,
+        name: String,
+    },
+    UnsupportedFormat {
+        path: PathBuf,
+        extension: String,
+    },
+    // errors of the OBJ, PLY, STL and glTF importers
+    Import {
+        path: PathBuf,
+        source: anyhow::Error,
+    },
+}
+
+impl SceneError {
+    pub fn path(&self) -> &Path {
+        match self {
+            SceneError::Io { path, .. }
+            | SceneError::Xml { path, .. }
+            | SceneError::Invalid { path, .. }
+            | SceneError::MissingElement { path, .. }
+            | SceneError::MissingAttribute { path, .. }
+            | SceneError::BadNumber { path, .. }
+            | SceneError::UnknownMaterial { path, .. }
+            | SceneError::DuplicateMaterial { path, .. }
+            | SceneError::UnsupportedFormat { path, .. }
+            | SceneError::Import { path, .. } => path,
+        }
+    }
+
+    // Ordnet einen Fehler ohne Pfad der Datei file zu
+    pub fn in_file(mut self, file: &Path) -> Self {
+        let path = match &mut self {
+            SceneError::Io { path, .. }
+            | SceneError::Xml { path, .. }
+            | SceneError::Invalid { path, .. }
+            | SceneError::MissingElement { path, .. }
+            | SceneError::MissingAttribute { path, .. }
+            | SceneError::BadNumber { path, .. }
+            | SceneError::UnknownMaterial { path, .. }
+            | SceneError::DuplicateMaterial { path, .. }
+            | SceneError::UnsupportedFormat { path, .. }
+            | SceneError::Import { path, .. } => path,
+        };
+        if path.as_os_str().is_empty() {
+            *path = file.to_path_buf();
+        }
+        self
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.path().as_os_str().is_empty() {
+            write!(f, "{}: ", self.path().display())?;
+        }
+        let at = |pos: &TextPos| format!("line {}, column {}", pos.row, pos.col);
+        match self {
+            SceneError::Io { .. } => write!(f, "cannot read file"),
+            SceneError::Xml { error, .. } => write!(f, "{}", error),
+            SceneError::Invalid { errors, .. } => {
+                write!(f, "violates the DTD")?;
+                errors.iter().try_for_each(|e| write!(f, "\n  {}", e))
+            }
+            SceneError::MissingElement {
+                pos, parent, name, ..
+            } => write!(
+                f,
+                "{}: <{}> has no child element <{}>",
+                at(pos),
+                parent,
+                name
+            ),
+            SceneError::MissingAttribute {
+                pos, element, name, ..
+            } => write!(f, "{}: <{}> has no attribute {}", at(pos), element, name),
+            SceneError::BadNumber {
+                pos,
+                attribute,
+                value,
+                ..
+            } => write!(
+                f,
+                "{}: invalid number {} in attribute {}",
+                at(pos),
+                value,
+                attribute
+            ),
+            SceneError::UnknownMaterial { pos, name, .. } => {
+                write!(f, "{}: unknown material {}", at(pos), name)
+            }
+            SceneError::DuplicateMaterial { pos, name, .. } => {
+                write!(f, "{}: duplicate material {}", at(pos), name)
+            }
+            SceneError::UnsupportedFormat { extension, .. } => {
+                write!(f, "unsupported triangulation format {}", extension)
+            }
+            SceneError::Import { .. } => write!(f, "cannot import triangulation"),
+        }
+    }
+}
+
+// the cause of Io and Import is not part of the message, print the chain, e.g. with {:#} of anyhow
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Import { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
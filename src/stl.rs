@@ -0,0 +1,137 @@
+// Import von STL-Dateien (ASCII und binär), wie sie CAD-Programme exportieren
+
+use crate::material::Material;
+use crate::mesh_processing::generate_missing_normals;
+use crate::triangle::Triangle;
+use anyhow::anyhow;
+use glam::f32::Vec3A;
+use std::fs;
+use std::path::Path;
+
+// 80 byte header and the number of triangles
+const BINARY_HEADER: usize = 84;
+// normal, three vertices and a 16 bit attribute
+const BINARY_TRIANGLE: usize = 50;
+
+#[test]
+fn test_parse_ascii() {
+    const STL: &str = "solid keil
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 -1 0
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 0 1
+    endloop
+  endfacet
+endsolid keil
+";
+    let (materials, triangles) = parse_stl(STL.as_bytes()).unwrap();
+    assert_eq!(materials.len(), 1);
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(triangles[1].p[2], Vec3A::Z);
+    // the two faces meet at a right angle, so the edge stays sharp
+    assert_eq!(triangles[0].n, [Vec3A::Z; 3]);
+    assert_eq!(triangles[1].n, [-Vec3A::Y; 3]);
+    assert!(parse_stl(b"solid x\nfacet normal 0 0 1\nouter loop\nvertex 0 0\n").is_err());
+}
+
+#[test]
+fn test_parse_binary() {
+    // binary files may start with "solid" as well
+    let mut stl = b"solid but binary".to_vec();
+    stl.resize(80, 0);
+    stl.extend(1u32.to_le_bytes());
+    for x in [0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0] {
+        stl.extend(x.to_le_bytes());
+    }
+    stl.extend([0, 0]);
+    let (_, triangles) = parse_stl(&stl).unwrap();
+    assert_eq!(triangles.len(), 1);
+    assert_eq!(triangles[0].p[2], Vec3A::new(0.0, 2.0, 0.0));
+    assert_eq!(triangles[0].n, [Vec3A::Z; 3]);
+    stl.push(0);
+    assert!(parse_stl(&stl).is_err());
+}
+
+// Binärdateien werden an ihrer Größe erkannt, da auch sie mit "solid" beginnen dürfen
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < BINARY_HEADER {
+        return false;
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    data.len() == BINARY_HEADER + count * BINARY_TRIANGLE
+}
+
+fn parse_binary(data: &[u8]) -> Vec<[Vec3A; 3]> {
+    data[BINARY_HEADER..]
+        .chunks_exact(BINARY_TRIANGLE)
+        .map(|record| {
+            let float = |i: usize| {
+                let b = &record[4 * i..4 * i + 4];
+                f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+            };
+            // the facet normal in floats 0 to 2 is recomputed from the vertices
+            let vertex = |v: usize| Vec3A::new(float(3 * v), float(3 * v + 1), float(3 * v + 2));
+            [vertex(1), vertex(2), vertex(3)]
+        })
+        .collect()
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<[Vec3A; 3]>, anyhow::Error> {
+    let text = std::str::from_utf8(data)?;
+    let mut tokens = text.split_ascii_whitespace();
+    if tokens.next() != Some("solid") {
+        return Err(anyhow!("Not an STL file"));
+    }
+    let mut facets = Vec::new();
+    let mut vertices: Vec<Vec3A> = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "vertex" => {
+                let mut coordinate = || -> Result<f32, anyhow::Error> {
+                    let t = tokens.next().ok_or(anyhow!("Unexpected end of STL data"))?;
+                    t.parse()
+                        .map_err(|_| anyhow!("Invalid number in STL data: {}", t))
+                };
+                vertices.push(Vec3A::new(coordinate()?, coordinate()?, coordinate()?));
+            }
+            // some exporters write polygons, which are split into a fan
+            "endfacet" => {
+                for k in 1..vertices.len().saturating_sub(1) {
+                    facets.push([vertices[0], vertices[k], vertices[k + 1]]);
+                }
+                vertices.clear();
+            }
+            _ => {}
+        }
+    }
+    if !vertices.is_empty() {
+        return Err(anyhow!("Unexpected end of STL data"));
+    }
+    Ok(facets)
+}
+
+// Liest die Dreiecke einer STL-Datei. STL kennt keine Materialien, alle Dreiecke erhalten Material::default_grey.
+// Die Normalen werden aus den Nachbardreiecken berechnet, Kanten von CAD-Modellen bleiben dabei scharf.
+pub fn parse_stl(data: &[u8]) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    let facets = if is_binary(data) {
+        parse_binary(data)
+    } else {
+        parse_ascii(data)?
+    };
+    let mut triangles: Vec<Triangle> = facets.into_iter().map(Triangle::from_array).collect();
+    let all: Vec<usize> = (0..triangles.len()).collect();
+    generate_missing_normals(&mut triangles, &all);
+    Ok((vec![Material::default_grey()], triangles))
+}
+
+pub fn load_stl(path: &Path) -> Result<(Vec<Material>, Vec<Triangle>), anyhow::Error> {
+    parse_stl(&fs::read(path)?)
+}
@@ -1,6 +1,8 @@
+use crate::ray::Ray;
 use glam::f32::Vec3A;
 
 #[test]
+#[allow(non_snake_case)]
 fn testNormal() {
     let t = Triangle::new(
         Vec3A::new(-1.0, 0.0, 0.0),
@@ -14,23 +16,103 @@ fn testNormal() {
     assert!(normal.z != 0.0);
 }
 
-#[derive(Debug)]
+#[test]
+fn test_intersect() {
+    let t = Triangle::new(
+        Vec3A::new(-1.0, 0.0, 0.0),
+        Vec3A::new(1.0, 0.0, 0.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+    );
+    let hit = Ray::new(Vec3A::new(0.0, 0.5, 2.0), Vec3A::new(0.0, 0.0, -1.0));
+    let (dist, u, v) = t.intersect(&hit).unwrap();
+    assert_eq!(dist, 2.0);
+    assert_eq!((u, v), (0.25, 0.5));
+    let miss = Ray::new(Vec3A::new(2.0, 0.5, 2.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(t.intersect(&miss).is_none());
+    let behind = Ray::new(Vec3A::new(0.0, 0.5, -2.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(t.intersect(&behind).is_none());
+}
+
+#[test]
+fn test_shading_normal() {
+    let t = Triangle::with_normals(
+        [Vec3A::ZERO, Vec3A::X, Vec3A::Y],
+        [Vec3A::new(-1.0, 0.0, 1.0), Vec3A::new(1.0, 0.0, 1.0), Vec3A::new(0.0, 2.0, 2.0)],
+    );
+    assert_eq!(t.normal(), Vec3A::Z);
+    let at_corner = t.shading_normal(0.0, 0.0);
+    assert!((at_corner - Vec3A::new(-1.0, 0.0, 1.0).normalize()).length() < 1e-6);
+    // halfway between the first two vertices the x components cancel out
+    assert!((t.shading_normal(0.5, 0.0) - Vec3A::Z).length() < 1e-6);
+    assert!((t.shading_normal(0.0, 1.0).length() - 1.0).abs() < 1e-6);
+}
+
+#[derive(Debug, Clone)]
 pub struct Triangle {
     pub p: [Vec3A; 3],
     pub n: [Vec3A; 3],
+    // index into Scene::materials
+    pub material: usize,
 }
 
 impl Triangle {
     pub fn from_array(p: [Vec3A; 3]) -> Self {
         let normal = (p[1] - p[0]).cross(p[2] - p[1]);
-        // we don't have information about neighbouring triangles, so set all normals to the same value
+        // we don't have information about neighbouring triangles, so set all normals to the same value,
+        // mesh_processing::compute_normals can smooth them afterwards
         let n: [Vec3A; 3] = [normal; 3];
-        Triangle { p, n }
+        Triangle { p, n, material: 0 }
     }
     pub fn with_normals(p: [Vec3A; 3], n: [Vec3A; 3]) -> Self {
-        Self { p, n }
+        Self { p, n, material: 0 }
     }
+    #[cfg(test)]
     pub fn new(a: Vec3A, b: Vec3A, c: Vec3A) -> Self {
         Self::from_array([a, b, c])
     }
+
+    // normierte Flächennormale, richtet sich nach dem Umlaufsinn der Punkte
+    pub fn normal(&self) -> Vec3A {
+        (self.p[1] - self.p[0]).cross(self.p[2] - self.p[0]).normalize()
+    }
+
+    // normierte, aus den Eckpunktnormalen baryzentrisch interpolierte Normale für weiche Schattierung
+    pub fn shading_normal(&self, u: f32, v: f32) -> Vec3A {
+        let n = (1.0 - u - v) * self.n[0] + u * self.n[1] + v * self.n[2];
+        n.try_normalize().unwrap_or_else(|| self.normal())
+    }
+
+    // Möller-Trumbore: liefert den Abstand t entlang des Strahls und die baryzentrischen Koordinaten (u, v) des Schnittpunkts
+    #[cfg(test)]
+    pub fn intersect(&self, ray: &Ray) -> Option<(f32, f32, f32)> {
+        intersect(&self.p, ray)
+    }
+}
+
+// Triangle::intersect für die Eckpunkte p, ohne dass ein Triangle angelegt werden muss
+pub fn intersect(p: &[Vec3A; 3], ray: &Ray) -> Option<(f32, f32, f32)> {
+    let e1 = p[1] - p[0];
+    let e2 = p[2] - p[0];
+    let pvec = ray.dir.cross(e2);
+    let det = e1.dot(pvec);
+    // ray parallel to the triangle plane
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = ray.origin - p[0];
+    let u = tvec.dot(pvec) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let qvec = tvec.cross(e1);
+    let v = ray.dir.dot(qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(qvec) * inv_det;
+    if t <= 0.0 {
+        return None;
+    }
+    Some((t, u, v))
 }
@@ -0,0 +1,317 @@
+// Rekursives Raytracing nach Whitted: Phong-Beleuchtung mit Schattenstrahlen, Spiegelung und Brechung
+
+use crate::color::Color;
+use crate::light_source::Light;
+use crate::material::Material;
+use crate::ray::{Intersection, Ray, EPSILON};
+use crate::sampling::{Pattern, Rng, Sampler};
+use crate::scene::Scene;
+use glam::f32::Vec3A;
+
+#[cfg(test)]
+fn test_scene(material: Material) -> Scene {
+    use crate::camera::Camera;
+    use crate::light_source::{Light, LightSource};
+    use crate::triangle::Triangle;
+    let quad = |z: f32, material: usize| {
+        let p = |x, y| Vec3A::new(x, y, z);
+        let mut a = Triangle::new(p(-1.0, -1.0), p(1.0, -1.0), p(1.0, 1.0));
+        let mut b = Triangle::new(p(-1.0, -1.0), p(1.0, 1.0), p(-1.0, 1.0));
+        a.material = material;
+        b.material = material;
+        [a, b]
+    };
+    let mut scene = Scene::new(
+        Camera::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::ZERO),
+        vec![material],
+        quad(0.0, 0).to_vec(),
+    );
+    scene.ambient = Color::new(0.5, 0.5, 0.5, 1.0);
+    scene.background = Color::new(0.0, 0.0, 1.0, 1.0);
+    scene.lights.push(Light::Point(LightSource {
+        pos: Vec3A::new(0.0, 0.0, 2.0),
+        color: Color::white(),
+    }));
+    scene
+}
+
+#[cfg(test)]
+fn assert_color_eq(a: Color, b: Color) {
+    let d = (a.r - b.r).abs() + (a.g - b.g).abs() + (a.b - b.b).abs();
+    assert!(d < 1e-5, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn test_phong() {
+    let mut material = Material::new("grau", Color::new(0.2, 0.4, 0.6, 1.0));
+    material.spiegelnd = Color::new(1.0, 1.0, 1.0, 1.0);
+    let mut scene = test_scene(material);
+    scene.attenuation.quadratisch = 1.0;
+    let ray = Ray::new(scene.camera.pos, -Vec3A::Z);
+    // ambient + (diffuse + specular) / (1 + 2²), light, normal and reflection all point at the camera
+    let expected = Color::new(0.1, 0.2, 0.3, 1.0) + Color::new(1.2, 1.4, 1.6, 1.0) * 0.2;
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), expected);
+    assert_color_eq(
+        trace(&scene, &Ray::new(scene.camera.pos, Vec3A::Z), 0, &mut Rng::new(0)),
+        scene.background,
+    );
+}
+
+#[test]
+fn test_shadow() {
+    use crate::triangle::Triangle;
+    let mut scene = test_scene(Material::new("grau", Color::new(0.2, 0.4, 0.6, 1.0)));
+    // small triangle between the light and the hit point, beside the camera ray
+    let mut blocker = Triangle::new(
+        Vec3A::new(-0.1, -0.1, 1.0),
+        Vec3A::new(0.1, -0.1, 1.0),
+        Vec3A::new(0.0, 0.1, 1.0),
+    );
+    blocker.material = 0;
+    scene.mesh.push(&blocker);
+    scene.build_accelerator(crate::accelerator::AcceleratorKind::BruteForce);
+    let ray = Ray::new(Vec3A::new(0.8, 0.0, 5.0), Vec3A::new(-0.8, 0.0, -5.0));
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), Color::new(0.1, 0.2, 0.3, 1.0));
+}
+
+#[test]
+fn test_soft_shadow() {
+    use crate::light_source::{AreaLight, Light, Shape};
+    use crate::triangle::Triangle;
+    let mut scene = test_scene(Material::new("grau", Color::new(0.2, 0.4, 0.6, 1.0)));
+    scene.ambient = Color::black();
+    scene.lights = vec![Light::Area(AreaLight {
+        shape: Shape::Rectangle {
+            center: Vec3A::new(0.0, 0.0, 2.0),
+            // facing down towards the plane
+            edge1: Vec3A::new(0.0, 1.0, 0.0),
+            edge2: Vec3A::new(1.0, 0.0, 0.0),
+        },
+        color: Color::white(),
+        samples: 64,
+    })];
+    let ray = Ray::new(scene.camera.pos, -Vec3A::Z);
+    let lit = trace(&scene, &ray, 0, &mut Rng::new(0));
+    // a blocker covering the left half of the light seen from the origin halves the light
+    let mut blocker = Triangle::new(
+        Vec3A::new(0.0, -5.0, 1.0),
+        Vec3A::new(-5.0, 0.0, 1.0),
+        Vec3A::new(0.0, 5.0, 1.0),
+    );
+    blocker.material = 0;
+    scene.mesh.push(&blocker);
+    scene.build_accelerator(crate::accelerator::AcceleratorKind::BruteForce);
+    let ray = Ray::new(Vec3A::new(0.5, 0.0, 5.0), Vec3A::new(-0.5, 0.0, -5.0).normalize());
+    let penumbra = trace(&scene, &ray, 0, &mut Rng::new(0));
+    assert!((penumbra.b / lit.b - 0.5).abs() < 0.05, "{:?} {:?}", penumbra, lit);
+}
+
+#[test]
+fn test_directional_and_spot() {
+    use crate::light_source::{DirectionalLight, Light, SpotLight};
+    use crate::triangle::Triangle;
+    let mut scene = test_scene(Material::new("grau", Color::new(0.2, 0.4, 0.6, 1.0)));
+    let ambient = Color::new(0.1, 0.2, 0.3, 1.0);
+    let ray = Ray::new(scene.camera.pos, -Vec3A::Z);
+    let sun = Light::Directional(DirectionalLight {
+        dir: Vec3A::new(0.0, 0.0, -3.0),
+        color: Color::white(),
+    });
+    scene.lights = vec![sun];
+    // no attenuation, however far away the sun is
+    scene.attenuation.quadratisch = 1.0;
+    let lit = ambient + Color::new(0.2, 0.4, 0.6, 1.0);
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), lit);
+    let spot = |dir| {
+        Light::Spot(SpotLight {
+            pos: Vec3A::new(0.0, 0.0, 1.0),
+            dir,
+            color: Color::white(),
+            angle: 20f32.to_radians(),
+            falloff: 1.0,
+        })
+    };
+    scene.lights = vec![spot(-Vec3A::Z)];
+    let expected = ambient + Color::new(0.2, 0.4, 0.6, 1.0) * 0.5;
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), expected);
+    // pointing past the hit point
+    scene.lights = vec![spot(Vec3A::new(1.0, 0.0, -1.0))];
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), ambient);
+    // the sun is shadowed by a blocker far above the camera
+    let mut blocker = Triangle::new(
+        Vec3A::new(-1.0, -1.0, 50.0),
+        Vec3A::new(1.0, -1.0, 50.0),
+        Vec3A::new(0.0, 1.0, 50.0),
+    );
+    blocker.material = 0;
+    scene.mesh.push(&blocker);
+    scene.build_accelerator(crate::accelerator::AcceleratorKind::BruteForce);
+    scene.lights = vec![Light::Directional(DirectionalLight {
+        dir: -Vec3A::Z,
+        color: Color::white(),
+    })];
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), ambient);
+}
+
+#[test]
+fn test_reflection_and_refraction() {
+    let mut mirror = Material::new("spiegel", Color::black());
+    mirror.spiegelnd = Color::new(1.0, 1.0, 1.0, 0.0);
+    let scene = test_scene(mirror);
+    // the mirror faces the camera, the reflected ray leaves the scene towards the background
+    let ray = Ray::new(scene.camera.pos, -Vec3A::Z);
+    let direct = trace(&scene, &ray, 0, &mut Rng::new(0));
+    assert_color_eq(trace(&scene, &ray, 1, &mut Rng::new(0)), direct + scene.background);
+
+    let mut glass = Material::new("glas", Color::black());
+    glass.diffus.a = 0.0;
+    let scene = test_scene(glass);
+    // fully transparent, so only the background behind the surface remains
+    assert_color_eq(trace(&scene, &ray, 1, &mut Rng::new(0)), scene.background);
+    assert_color_eq(trace(&scene, &ray, 0, &mut Rng::new(0)), Color::black());
+}
+
+#[test]
+fn test_refract() {
+    let n = Vec3A::Y;
+    let straight = refract(-Vec3A::Y, n, 1.0 / 1.5).unwrap();
+    assert!((straight + Vec3A::Y).length() < 1e-6);
+    let d = Vec3A::new(1.0, -1.0, 0.0).normalize();
+    let bent = refract(d, n, 1.0 / 1.5).unwrap();
+    // Snell: sin(out) = sin(in) / 1.5
+    assert!((bent.x - d.x / 1.5).abs() < 1e-6);
+    // total internal reflection when leaving the denser medium at a flat angle
+    assert!(refract(d, n, 1.5).is_none());
+}
+
+pub fn reflect(d: Vec3A, n: Vec3A) -> Vec3A {
+    d - 2.0 * d.dot(n) * n
+}
+
+// Richtung des gebrochenen Strahls nach Snellius, eta = n1 / n2. None bei Totalreflexion.
+pub fn refract(d: Vec3A, n: Vec3A, eta: f32) -> Option<Vec3A> {
+    let cos_i = -d.dot(n);
+    let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+    if k < 0.0 {
+        return None;
+    }
+    Some((eta * d + (eta * cos_i - k.sqrt()) * n).normalize())
+}
+
+// Summe von shade(light, l, color) über die Abtastpunkte aller Lichtquellen, die vom Punkt p aus in Richtung l
+// oberhalb der Fläche sichtbar sind. normal ist die Flächennormale, n die Schattierungsnormale. Flächenlichtquellen
+// werden mit geschichteten Schattenstrahlen abgetastet, was weiche Schatten ergibt.
+pub fn sample_lights(
+    scene: &Scene,
+    p: Vec3A,
+    normal: Vec3A,
+    n: Vec3A,
+    rng: &mut Rng,
+    shade: impl Fn(&Light, Vec3A, Color) -> Color,
+) -> Color {
+    // light of color arriving from direction l, if it is not shadowed
+    let illuminate = |light: &Light, l: Vec3A, distance: f32, color: Color| {
+        if n.dot(l) <= 0.0 || distance <= EPSILON {
+            return Color::black();
+        }
+        // stops short of the light, so that emissive triangles do not shadow themselves
+        let shadow_ray = Ray::new(p + EPSILON * normal, l);
+        if scene.intersect(&shadow_ray, distance - EPSILON).is_some() {
+            return Color::black();
+        }
+        shade(light, l, color)
+    };
+    let mut color = Color::black();
+    for light in &scene.lights {
+        let samples = light.samples();
+        let mut sampler = Sampler::new(Pattern::Stratified, samples, rng);
+        for i in 0..samples {
+            let (u, v) = match samples {
+                1 => (0.5, 0.5),
+                _ => sampler.sample(i, rng),
+            };
+            if let Some((l, distance, c)) = light.incident(u, v, p, &scene.attenuation) {
+                color = color + illuminate(light, l, distance, c * (1.0 / samples as f32));
+            }
+        }
+    }
+    color
+}
+
+// Diffuses und spiegelndes Licht nach Phong von allen Lichtquellen, die vom Punkt p aus sichtbar sind
+pub fn direct_light(
+    scene: &Scene,
+    material: &Material,
+    p: Vec3A,
+    normal: Vec3A,
+    n: Vec3A,
+    view: Vec3A,
+    rng: &mut Rng,
+) -> Color {
+    sample_lights(scene, p, normal, n, rng, |_, l, color| {
+        let cos_alpha = reflect(-l, n).dot(view).max(0.0);
+        let diffuse = material.diffus * n.dot(l);
+        let specular = material.spiegelnd * cos_alpha.powf(material.glanz);
+        color * (diffuse + specular)
+    })
+}
+
+// Phong-Beleuchtung mit dem konstanten ambienten Anteil der Szene
+fn phong(
+    scene: &Scene,
+    material: &Material,
+    p: Vec3A,
+    normal: Vec3A,
+    n: Vec3A,
+    view: Vec3A,
+    rng: &mut Rng,
+) -> Color {
+    material.ambient * scene.ambient + direct_light(scene, material, p, normal, n, view, rng)
+}
+
+// Farbe entlang des Strahls, depth ist die Anzahl der noch erlaubten Spiegelungen und Brechungen. rng liefert die
+// Schattenstrahlen zu Flächenlichtquellen.
+pub fn trace(scene: &Scene, ray: &Ray, depth: u32, rng: &mut Rng) -> Color {
+    let hit = match scene.intersect(ray, scene.camera.clippingFar) {
+        Some(hit) => hit,
+        None => return scene.background,
+    };
+    let triangle = scene.mesh.triangle(hit.triangle);
+    let material = scene.material(&triangle);
+    let Intersection {
+        p,
+        mut normal,
+        shading_normal: mut n,
+        ..
+    } = Intersection::new(ray, hit, &triangle);
+    // leaving the object through its back face
+    let inside = normal.dot(ray.dir) > 0.0;
+    if inside {
+        normal = -normal;
+        n = -n;
+    }
+    let opacity = 1.0 - material.transparency();
+    let mut color = phong(scene, material, p, normal, n, -ray.dir, rng) * opacity
+        + scene.emission(triangle.material);
+    if depth == 0 {
+        return color;
+    }
+    let mirrored = Ray::new(p + EPSILON * normal, reflect(ray.dir, n));
+    if material.isSpiegelnd() {
+        let reflected = trace(scene, &mirrored, depth - 1, rng);
+        color = color + reflected * material.spiegelnd * material.reflectance();
+    }
+    if material.isTransparent() {
+        let eta = if inside {
+            material.brechungsindex
+        } else {
+            1.0 / material.brechungsindex
+        };
+        let transmitted = match refract(ray.dir, n, eta) {
+            Some(dir) => trace(scene, &Ray::new(p - EPSILON * normal, dir), depth - 1, rng),
+            None => trace(scene, &mirrored, depth - 1, rng),
+        };
+        color = color + transmitted * material.transparency();
+    }
+    color
+}